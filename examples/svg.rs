@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use flicker::{Affine, Color, Font, Point, Renderer};
+use flicker::{Affine, Color, CompositeOp, Font, Point, Renderer};
 use portlight::{App, Bitmap, Event, MouseButton, Response, Size, WindowContext, WindowOptions};
 
 const WIDTH: usize = 512;
@@ -98,6 +98,7 @@ impl State {
                     24.0,
                     Affine::scale(scale as f32),
                     Color::rgba(0, 0, 0, 255),
+                    CompositeOp::SrcOver,
                 );
 
                 cx.window().present(Bitmap::new(&self.framebuffer, width, height));
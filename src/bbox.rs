@@ -1,7 +1,9 @@
 use crate::geom::{Affine, Point};
 use crate::path::Path;
+use crate::stroke::{LineCap, LineJoin, StrokeStyle};
 
-#[derive(Copy, Clone)]
+/// An axis-aligned rectangle of pixels, from (`x0`, `y0`) inclusive to (`x1`, `y1`) exclusive.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Bbox {
     pub x0: i32,
     pub y0: i32,
@@ -14,6 +16,17 @@ impl Bbox {
     pub fn is_empty(&self) -> bool {
         self.x1 <= self.x0 || self.y1 <= self.y0
     }
+
+    /// Returns the area covered by both rectangles, which may be empty.
+    #[inline]
+    pub fn intersect(&self, other: Bbox) -> Bbox {
+        Bbox {
+            x0: self.x0.max(other.x0),
+            y0: self.y0.max(other.y0),
+            x1: self.x1.min(other.x1),
+            y1: self.y1.min(other.y1),
+        }
+    }
 }
 
 pub fn fill(path: &Path, transform: Affine, clip: Bbox) -> Bbox {
@@ -33,9 +46,21 @@ pub fn fill(path: &Path, transform: Affine, clip: Bbox) -> Bbox {
     }
 }
 
-pub fn stroke(path: &Path, width: f32, transform: Affine, clip: Bbox) -> Bbox {
-    let dilate_x = transform.linear() * width * Point::new(0.5, 0.0);
-    let dilate_y = transform.linear() * width * Point::new(0.0, 0.5);
+pub fn stroke(path: &Path, style: &StrokeStyle, transform: Affine, clip: Bbox) -> Bbox {
+    // Miter joins can extend up to the miter limit times the half-width away from the path, and
+    // the corners of square caps can extend up to sqrt(2) times the half-width.
+    let join_factor = match style.join {
+        LineJoin::Miter => style.miter_limit.max(1.0),
+        LineJoin::Round | LineJoin::Bevel => 1.0,
+    };
+    let cap_factor = match style.cap {
+        LineCap::Square => std::f32::consts::SQRT_2,
+        LineCap::Butt | LineCap::Round => 1.0,
+    };
+    let extent = 0.5 * style.width * join_factor.max(cap_factor);
+
+    let dilate_x = transform.linear() * extent * Point::new(1.0, 0.0);
+    let dilate_y = transform.linear() * extent * Point::new(0.0, 1.0);
     let dilate_min = dilate_x.min(dilate_y).min(-dilate_x).min(-dilate_y);
     let dilate_max = dilate_x.max(dilate_y).max(-dilate_x).max(-dilate_y);
 
@@ -0,0 +1,104 @@
+/// Determines how the colors being drawn are combined with the existing contents of the target.
+///
+/// Operators only affect the pixels covered by the shape being drawn. Coverage is applied by
+/// interpolating between the existing color and the result of the operator.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum CompositeOp {
+    /// Clears the destination.
+    Clear,
+    /// Replaces the destination with the source.
+    Src,
+    /// Draws the source over the destination.
+    #[default]
+    SrcOver,
+    /// Draws the source where the destination is present.
+    SrcIn,
+    /// Draws the source where the destination is absent.
+    SrcOut,
+    /// Draws the source over the destination, where the destination is present.
+    SrcAtop,
+    /// Draws the destination over the source.
+    DstOver,
+    /// Keeps the destination where the source is present.
+    DstIn,
+    /// Keeps the destination where the source is absent.
+    DstOut,
+    /// Draws the destination over the source, where the source is present.
+    DstAtop,
+    /// Keeps the source and destination where the other is absent.
+    Xor,
+    /// Adds the source and destination.
+    Plus,
+}
+
+/// Determines how the color being drawn is mixed with the existing color before compositing, as
+/// defined by the W3C Compositing and Blending specification.
+///
+/// Where the destination is transparent, the source color is used unchanged.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Uses the source color.
+    #[default]
+    Normal,
+    /// Multiplies the source and destination colors.
+    Multiply,
+    /// Inverts, multiplies and inverts the source and destination colors.
+    Screen,
+    /// Multiplies or screens the colors, depending on the destination color.
+    Overlay,
+    /// Uses the darker of the source and destination colors.
+    Darken,
+    /// Uses the lighter of the source and destination colors.
+    Lighten,
+    /// Brightens the destination color to reflect the source color.
+    ColorDodge,
+    /// Darkens the destination color to reflect the source color.
+    ColorBurn,
+    /// Multiplies or screens the colors, depending on the source color.
+    HardLight,
+    /// Darkens or lightens the colors, depending on the source color.
+    SoftLight,
+    /// Subtracts the darker of the two colors from the lighter.
+    Difference,
+    /// Like `Difference`, but with lower contrast.
+    Exclusion,
+    /// Uses the hue of the source with the saturation and luminosity of the destination.
+    Hue,
+    /// Uses the saturation of the source with the hue and luminosity of the destination.
+    Saturation,
+    /// Uses the hue and saturation of the source with the luminosity of the destination.
+    Color,
+    /// Uses the luminosity of the source with the hue and saturation of the destination.
+    Luminosity,
+}
+
+/// A blend mode together with the composite operator applied to its result.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Blend {
+    pub mode: BlendMode,
+    pub op: CompositeOp,
+}
+
+impl Blend {
+    pub fn new(mode: BlendMode, op: CompositeOp) -> Blend {
+        Blend { mode, op }
+    }
+}
+
+impl From<CompositeOp> for Blend {
+    fn from(op: CompositeOp) -> Blend {
+        Blend {
+            mode: BlendMode::Normal,
+            op,
+        }
+    }
+}
+
+impl From<BlendMode> for Blend {
+    fn from(mode: BlendMode) -> Blend {
+        Blend {
+            mode,
+            op: CompositeOp::SrcOver,
+        }
+    }
+}
@@ -0,0 +1,171 @@
+/// The number of box blurs used to approximate a Gaussian blur.
+const PASSES: usize = 3;
+
+/// Returns the widths of the box blurs which together approximate a Gaussian blur with standard
+/// deviation `sigma`.
+fn box_widths(sigma: f32) -> [usize; PASSES] {
+    if !sigma.is_finite() || sigma <= 0.0 {
+        return [1; PASSES];
+    }
+
+    // See "Fast Almost-Gaussian Filtering" (Kovesi, 2010).
+    let n = PASSES as f32;
+    let variance = 12.0 * sigma * sigma;
+    let ideal = (variance / n + 1.0).sqrt();
+    let mut lower = ideal.floor() as usize;
+    if lower.is_multiple_of(2) {
+        lower -= 1;
+    }
+    let upper = lower + 2;
+
+    let l = lower as f32;
+    let lower_count = ((variance - n * l * l - 4.0 * n * l - 3.0 * n) / (-4.0 * l - 4.0)).round();
+
+    let mut widths = [upper; PASSES];
+    for (i, width) in widths.iter_mut().enumerate() {
+        if (i as f32) < lower_count {
+            *width = lower;
+        }
+    }
+    widths
+}
+
+/// Returns the distance by which a blur with standard deviation `sigma` spreads out, which is zero
+/// if the blur leaves values unchanged.
+pub fn extent(sigma: f32) -> usize {
+    box_widths(sigma).iter().map(|width| width / 2).sum()
+}
+
+/// Blurs `width` by `height` values in place, treating everything outside of them as zero.
+pub fn blur(data: &mut [f32], width: usize, height: usize, sigma: f32) {
+    let widths = box_widths(sigma);
+    if widths == [1; PASSES] {
+        return;
+    }
+
+    let mut line = vec![0.0; width.max(height)];
+    let mut temp = vec![0.0; width.max(height)];
+
+    for row in data.chunks_exact_mut(width).take(height) {
+        line[..width].copy_from_slice(row);
+        for &box_width in &widths {
+            box_blur(&line[..width], &mut temp[..width], box_width / 2);
+            line[..width].copy_from_slice(&temp[..width]);
+        }
+        row.copy_from_slice(&line[..width]);
+    }
+
+    for x in 0..width {
+        for y in 0..height {
+            line[y] = data[y * width + x];
+        }
+        for &box_width in &widths {
+            box_blur(&line[..height], &mut temp[..height], box_width / 2);
+            line[..height].copy_from_slice(&temp[..height]);
+        }
+        for y in 0..height {
+            data[y * width + x] = line[y].max(0.0);
+        }
+    }
+}
+
+/// Averages each value of `src` with the `radius` values on either side of it.
+fn box_blur(src: &[f32], dst: &mut [f32], radius: usize) {
+    let scale = 1.0 / (2 * radius + 1) as f32;
+
+    let mut sum: f32 = src.iter().take(radius).sum();
+    for i in 0..src.len() {
+        if i + radius < src.len() {
+            sum += src[i + radius];
+        }
+        dst[i] = sum * scale;
+        if i >= radius {
+            sum -= src[i - radius];
+        }
+    }
+}
+
+/// Blurs an 8-bit mask in place.
+pub fn blur_mask(data: &mut [u8], width: usize, height: usize, sigma: f32) {
+    let mut values: Vec<f32> = data.iter().map(|&value| value as f32).collect();
+    blur(&mut values, width, height, sigma);
+
+    for (value, &blurred) in data.iter_mut().zip(&values) {
+        *value = (blurred + 0.5).min(255.0) as u8;
+    }
+}
+
+/// Blurs premultiplied ARGB pixels in place, stored with a stride of `width`.
+pub fn blur_pixels(data: &mut [u32], width: usize, height: usize, sigma: f32) {
+    let mut channels: [Vec<f32>; 4] = [24, 16, 8, 0]
+        .map(|shift| data.iter().map(|&pixel| (pixel >> shift & 0xFF) as f32).collect());
+    for channel in &mut channels {
+        blur(channel, width, height, sigma);
+    }
+
+    for (i, pixel) in data.iter_mut().enumerate() {
+        let [a, r, g, b] = [0, 1, 2, 3].map(|c| (channels[c][i] + 0.5).min(255.0) as u32);
+        *pixel = a << 24 | r.min(a) << 16 | g.min(a) << 8 | b.min(a);
+    }
+}
+
+/// Blurs premultiplied RGBA float pixels in place, stored with a stride of `width`.
+pub fn blur_float_pixels(data: &mut [f32], width: usize, height: usize, sigma: f32) {
+    let mut channels: [Vec<f32>; 4] =
+        [0, 1, 2, 3].map(|c| data.iter().skip(c).step_by(4).copied().collect());
+    for channel in &mut channels {
+        blur(channel, width, height, sigma);
+    }
+
+    for (i, pixel) in data.chunks_exact_mut(4).enumerate() {
+        for (value, channel) in pixel.iter_mut().zip(&channels) {
+            *value = channel[i];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blur_keeps_total() {
+        let size = 41;
+        let center = 20 * size + 20;
+        for sigma in [1.0, 2.5, 4.0] {
+            let mut data = vec![0.0; size * size];
+            data[center] = 1.0;
+            blur(&mut data, size, size, sigma);
+
+            let total: f32 = data.iter().sum();
+            assert!((total - 1.0).abs() < 1e-5, "{}", total);
+
+            // The falloff is symmetric about the center in each direction and across the diagonal.
+            for y in 0..size {
+                for x in 0..size {
+                    let value = data[y * size + x];
+                    for mirrored in [y * size + (40 - x), (40 - y) * size + x, x * size + y] {
+                        assert!((data[mirrored] - value).abs() < 1e-6);
+                    }
+                }
+            }
+            // Values decrease away from the center, reaching zero just past the extent.
+            let extent = extent(sigma);
+            for x in 0..extent {
+                assert!(data[center + x] >= data[center + x + 1]);
+            }
+            assert!(data[center + extent] > 0.0);
+            assert!(data[center + extent + 1] < 1e-6);
+        }
+    }
+
+    #[test]
+    fn zero_sigma_leaves_values_unchanged() {
+        for sigma in [0.0, -1.0, f32::NAN] {
+            assert_eq!(extent(sigma), 0);
+            let mut data = vec![0.0, 1.0, 0.25, 0.5];
+            blur(&mut data, 2, 2, sigma);
+            assert_eq!(data, [0.0, 1.0, 0.25, 0.5]);
+        }
+    }
+}
@@ -1,4 +1,5 @@
-#[derive(Copy, Clone)]
+/// An 8-bit sRGB color with straight (non-premultiplied) alpha, stored as `0xAARRGGBB`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Color(u32);
 
 #[allow(clippy::identity_op)]
@@ -27,6 +28,17 @@ impl Color {
     pub fn a(&self) -> u8 {
         ((self.0 >> 24) & 0xFF) as u8
     }
+
+    /// Multiplies the color channels by alpha.
+    #[inline]
+    pub fn premultiply(self) -> PremulColor {
+        let a = self.a() as u32;
+        let mul = |c: u8| {
+            let t = c as u32 * a + 128;
+            ((t + (t >> 8)) >> 8) as u8
+        };
+        PremulColor::rgba(mul(self.r()), mul(self.g()), mul(self.b()), self.a())
+    }
 }
 
 impl From<u32> for Color {
@@ -40,3 +52,133 @@ impl From<Color> for u32 {
         color.0
     }
 }
+
+/// An 8-bit sRGB color with premultiplied alpha, stored as `0xAARRGGBB`. This is the format of the
+/// pixels written by `Renderer::attach` targets. Color channels are never greater than alpha.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct PremulColor(u32);
+
+#[allow(clippy::identity_op)]
+impl PremulColor {
+    /// Creates a color from premultiplied channels, which are clamped to `a`.
+    #[inline]
+    pub fn rgba(r: u8, g: u8, b: u8, a: u8) -> PremulColor {
+        let (r, g, b) = (r.min(a), g.min(a), b.min(a));
+        PremulColor(((a as u32) << 24) | ((r as u32) << 16) | ((g as u32) << 8) | ((b as u32) << 0))
+    }
+
+    #[inline]
+    pub fn r(&self) -> u8 {
+        ((self.0 >> 16) & 0xFF) as u8
+    }
+
+    #[inline]
+    pub fn g(&self) -> u8 {
+        ((self.0 >> 8) & 0xFF) as u8
+    }
+
+    #[inline]
+    pub fn b(&self) -> u8 {
+        ((self.0 >> 0) & 0xFF) as u8
+    }
+
+    #[inline]
+    pub fn a(&self) -> u8 {
+        ((self.0 >> 24) & 0xFF) as u8
+    }
+
+    /// Divides the color channels by alpha. Fully transparent colors become transparent black.
+    #[inline]
+    pub fn unpremultiply(self) -> Color {
+        let a = self.a() as u32;
+        if a == 0 {
+            return Color::rgba(0, 0, 0, 0);
+        }
+
+        let div = |c: u8| ((c as u32 * 255 + a / 2) / a).min(255) as u8;
+        Color::rgba(div(self.r()), div(self.g()), div(self.b()), self.a())
+    }
+
+    /// Views colors as `0xAARRGGBB` values.
+    #[inline]
+    pub(crate) fn as_argb(colors: &[PremulColor]) -> &[u32] {
+        // SAFETY: `PremulColor` is a transparent wrapper around `u32`.
+        unsafe { std::slice::from_raw_parts(colors.as_ptr() as *const u32, colors.len()) }
+    }
+
+    /// Views colors as `0xAARRGGBB` values which may be overwritten. Renderers only write values
+    /// with color channels no greater than alpha.
+    #[inline]
+    pub(crate) fn as_argb_mut(colors: &mut [PremulColor]) -> &mut [u32] {
+        // SAFETY: `PremulColor` is a transparent wrapper around `u32`.
+        unsafe { std::slice::from_raw_parts_mut(colors.as_mut_ptr() as *mut u32, colors.len()) }
+    }
+}
+
+impl From<Color> for PremulColor {
+    fn from(color: Color) -> PremulColor {
+        color.premultiply()
+    }
+}
+
+impl From<PremulColor> for Color {
+    fn from(color: PremulColor) -> Color {
+        color.unpremultiply()
+    }
+}
+
+impl From<u32> for PremulColor {
+    /// Clamps the color channels to alpha.
+    fn from(value: u32) -> PremulColor {
+        let [a, r, g, b] = value.to_be_bytes();
+        PremulColor::rgba(r, g, b, a)
+    }
+}
+
+impl From<PremulColor> for u32 {
+    fn from(color: PremulColor) -> u32 {
+        color.0
+    }
+}
+
+/// A sRGB color with straight (non-premultiplied) alpha and `f32` channels. Color channels may
+/// exceed 1, which float render targets keep, while 8-bit targets clamp them. Alpha lies between 0
+/// and 1.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ColorF32 {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl ColorF32 {
+    #[inline]
+    pub fn rgba(r: f32, g: f32, b: f32, a: f32) -> ColorF32 {
+        ColorF32 { r, g, b, a }
+    }
+
+    /// Returns the a, r, g, and b channels, with negative and NaN values replaced by 0 and alpha
+    /// limited to 1.
+    #[inline]
+    pub(crate) fn channels(self) -> [f32; 4] {
+        let a = if self.a > 0.0 { self.a.min(1.0) } else { 0.0 };
+        [a, self.r.max(0.0), self.g.max(0.0), self.b.max(0.0)]
+    }
+
+    /// Like `channels`, but with the color channels multiplied by alpha.
+    #[inline]
+    pub(crate) fn premultiplied(self) -> [f32; 4] {
+        let [a, r, g, b] = self.channels();
+        [a, a * r, a * g, a * b]
+    }
+}
+
+impl From<Color> for ColorF32 {
+    fn from(color: Color) -> ColorF32 {
+        let [a, r, g, b] =
+            [color.a(), color.r(), color.g(), color.b()].map(|c| c as f32 * (1.0 / 255.0));
+        ColorF32 { r, g, b, a }
+    }
+}
@@ -1,8 +1,13 @@
 use crate::geom::{Affine, Point};
 use crate::path::{Path, Verb};
+use crate::stroke::{Dash, LineCap, LineJoin, StrokeStyle};
 
 const TOLERANCE: f32 = 0.2;
 const MAX_SEGMENTS: usize = 100;
+/// The maximum number of dashes and gaps a path is split into. Paths which would need more are
+/// stroked without dashing, which also keeps tiny lengths from being lost to rounding when they are
+/// added up along a long segment.
+const MAX_DASHES: f32 = 1_000_000.0;
 
 trait Curve {
     fn transform(&self, transform: Affine) -> Self;
@@ -18,6 +23,10 @@ trait Curve {
     fn end_tangent(&self) -> Point;
 
     fn segments_for_tolerance(&self, tolerance: f32) -> usize;
+
+    /// Returns the part of the curve between `t0` and `t1`.
+    fn split(&self, t0: f32, t1: f32) -> Self;
+    fn to_cubic(&self) -> Cubic;
 }
 
 #[derive(Copy, Clone)]
@@ -75,6 +84,24 @@ impl Curve for Line {
     fn segments_for_tolerance(&self, _tolerance: f32) -> usize {
         1
     }
+
+    #[inline]
+    fn split(&self, t0: f32, t1: f32) -> Self {
+        Line {
+            p0: self.eval(t0),
+            p1: self.eval(t1),
+        }
+    }
+
+    #[inline]
+    fn to_cubic(&self) -> Cubic {
+        Cubic {
+            p0: self.p0,
+            p1: Point::lerp(1.0 / 3.0, self.p0, self.p1),
+            p2: Point::lerp(2.0 / 3.0, self.p0, self.p1),
+            p3: self.p1,
+        }
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -132,6 +159,28 @@ impl Curve for Quadratic {
 
         dt.recip().ceil() as usize
     }
+
+    #[inline]
+    fn split(&self, t0: f32, t1: f32) -> Self {
+        // The derivative of a quadratic is twice its tangent, and the control point of the part
+        // lies halfway along the derivative from its start.
+        let p0 = self.eval(t0);
+        Quadratic {
+            p0,
+            p1: p0 + (t1 - t0) * self.tangent(t0),
+            p2: self.eval(t1),
+        }
+    }
+
+    #[inline]
+    fn to_cubic(&self) -> Cubic {
+        Cubic {
+            p0: self.p0,
+            p1: Point::lerp(2.0 / 3.0, self.p0, self.p1),
+            p2: Point::lerp(2.0 / 3.0, self.p2, self.p1),
+            p3: self.p2,
+        }
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -199,6 +248,25 @@ impl Curve for Cubic {
 
         dt.recip().ceil() as usize
     }
+
+    #[inline]
+    fn split(&self, t0: f32, t1: f32) -> Self {
+        // The derivative of a cubic is three times its tangent, and the control points of the
+        // part lie a third of the way along the derivative from each end.
+        let p0 = self.eval(t0);
+        let p3 = self.eval(t1);
+        Cubic {
+            p0,
+            p1: p0 + (t1 - t0) * self.tangent(t0),
+            p2: p3 - (t1 - t0) * self.tangent(t1),
+            p3,
+        }
+    }
+
+    #[inline]
+    fn to_cubic(&self) -> Cubic {
+        *self
+    }
 }
 
 #[inline]
@@ -291,92 +359,300 @@ pub fn fill(path: &Path, transform: Affine, lines: &mut Vec<Line>) {
 
 struct Stroker<'a> {
     width: f32,
+    join: LineJoin,
+    cap: LineCap,
+    miter_limit: f32,
     transform: Affine,
+    scale: f32,
+    first_point: Point,
+    first_offset: Point,
     first_right: Point,
     first_left: Point,
+    prev_point: Point,
+    prev_offset: Point,
     prev_right: Point,
     prev_left: Point,
     closed: bool,
+    dot: Option<Point>,
     lines: &'a mut Vec<Line>,
 }
 
 impl Stroker<'_> {
     #[inline]
-    fn new(width: f32, transform: Affine, lines: &mut Vec<Line>) -> Stroker {
+    fn new<'a>(style: &StrokeStyle, transform: Affine, lines: &'a mut Vec<Line>) -> Stroker<'a> {
+        // Estimate how much the transform can stretch a vector, for choosing the number of segments
+        // used to approximate round joins.
+        let axis_x = transform.linear() * Point::new(1.0, 0.0);
+        let axis_y = transform.linear() * Point::new(0.0, 1.0);
+        let scale = axis_x.length().max(axis_y.length());
+
         Stroker {
-            width,
+            width: style.width,
+            join: style.join,
+            cap: style.cap,
+            miter_limit: style.miter_limit,
             transform,
+            scale,
+            first_point: Point::new(0.0, 0.0),
+            first_offset: Point::new(0.0, 0.0),
             first_right: Point::new(0.0, 0.0),
             first_left: Point::new(0.0, 0.0),
+            prev_point: Point::new(0.0, 0.0),
+            prev_offset: Point::new(0.0, 0.0),
             prev_right: Point::new(0.0, 0.0),
             prev_left: Point::new(0.0, 0.0),
             closed: true,
+            dot: None,
             lines,
         }
     }
 
+    /// Computes the offset from a point on the path to the right edge of the stroke, in
+    /// untransformed coordinates.
+    #[inline]
+    fn offset(&self, tangent: Point) -> Point {
+        let normal = Point::new(-tangent.y, tangent.x);
+        let normal_len = normal.length();
+        if normal_len.abs() < 1e-6 {
+            Point::new(0.0, 0.0)
+        } else {
+            0.5 * self.width * (1.0 / normal_len) * normal
+        }
+    }
+
+    /// Finds the number of segments needed to approximate an arc of the stroke's outline.
+    #[inline]
+    fn arc_segments(&self, angle: f32) -> usize {
+        let radius = 0.5 * self.width * self.scale;
+        if radius <= TOLERANCE {
+            return 1;
+        }
+
+        let max_angle = 2.0 * (1.0 - TOLERANCE / radius).acos();
+        ((angle.abs() / max_angle).ceil() as usize).clamp(1, MAX_SEGMENTS)
+    }
+
+    /// Pushes an outline edge, reversing it if it lies on the left side of the stroke.
+    #[inline]
+    fn push_edge(&mut self, p0: Point, p1: Point, left: bool) {
+        if left {
+            self.lines.push(Line::new(p1, p0));
+        } else {
+            self.lines.push(Line::new(p0, p1));
+        }
+    }
+
     #[inline]
     fn cap_begin(&mut self) {
-        self.lines.push(Line::new(self.first_left, self.first_right));
+        self.cap(
+            self.first_point,
+            -self.first_offset,
+            self.first_left,
+            self.first_right,
+        );
     }
 
     #[inline]
     fn cap_end(&mut self) {
-        self.lines.push(Line::new(self.prev_right, self.prev_left));
+        self.cap(
+            self.prev_point,
+            self.prev_offset,
+            self.prev_right,
+            self.prev_left,
+        );
+    }
+
+    /// Connects the two sides of the stroke at an endpoint, going from `p0` at `offset` from the
+    /// endpoint to `p1` at `-offset`, around the outside of the endpoint.
+    #[inline]
+    fn cap(&mut self, point: Point, offset: Point, p0: Point, p1: Point) {
+        if offset == Point::new(0.0, 0.0) {
+            self.lines.push(Line::new(p0, p1));
+            return;
+        }
+
+        // Points outward from the endpoint, away from the rest of the stroke.
+        let extension = Point::new(offset.y, -offset.x);
+
+        match self.cap {
+            LineCap::Butt => {
+                self.lines.push(Line::new(p0, p1));
+            }
+            LineCap::Square => {
+                let corner0 = self.transform * (point + offset + extension);
+                let corner1 = self.transform * (point - offset + extension);
+                self.lines.push(Line::new(p0, corner0));
+                self.lines.push(Line::new(corner0, corner1));
+                self.lines.push(Line::new(corner1, p1));
+            }
+            LineCap::Round => {
+                let angle = -std::f32::consts::PI;
+                let segments = self.arc_segments(angle);
+                let step = angle / segments as f32;
+                let (sin, cos) = step.sin_cos();
+
+                let mut vector = offset;
+                let mut prev = p0;
+                for _ in 1..segments {
+                    vector = Point::new(
+                        cos * vector.x - sin * vector.y,
+                        sin * vector.x + cos * vector.y,
+                    );
+                    let next = self.transform * (point + vector);
+                    self.lines.push(Line::new(prev, next));
+                    prev = next;
+                }
+                self.lines.push(Line::new(prev, p1));
+            }
+        }
+    }
+
+    /// Draws the caps for a subpath of zero length, oriented along the x-axis.
+    #[inline]
+    fn draw_dot(&mut self, point: Point) {
+        if self.cap == LineCap::Butt {
+            return;
+        }
+
+        let offset = Point::new(0.0, 0.5 * self.width);
+        let right = self.transform * (point + offset);
+        let left = self.transform * (point - offset);
+
+        self.first_point = point;
+        self.first_offset = offset;
+        self.first_right = right;
+        self.first_left = left;
+        self.prev_point = point;
+        self.prev_offset = offset;
+        self.prev_right = right;
+        self.prev_left = left;
+
+        self.cap_begin();
+        self.cap_end();
     }
 
+    /// Marks a point at which a zero-length subpath should be drawn, unless the current subpath
+    /// turns out to have nonzero length.
     #[inline]
-    fn join(&mut self, right: Point, left: Point) {
-        self.lines.push(Line::new(self.prev_right, right));
-        self.lines.push(Line::new(left, self.prev_left));
+    fn degenerate(&mut self, point: Point) {
+        if self.closed {
+            self.dot = Some(point);
+        }
+    }
+
+    #[inline]
+    fn join(&mut self, point: Point, offset: Point, right: Point, left: Point) {
+        let prev_offset = self.prev_offset;
+
+        // The inner side of the join is covered by the segments on either side of it, so the
+        // offset points can be connected directly. The join shape is only needed on the outer side.
+        if prev_offset.cross(offset) <= 0.0 {
+            self.lines.push(Line::new(left, self.prev_left));
+            self.join_outer(point, prev_offset, offset, self.prev_right, right, false);
+        } else {
+            self.lines.push(Line::new(self.prev_right, right));
+            self.join_outer(point, -prev_offset, -offset, self.prev_left, left, true);
+        }
+    }
+
+    #[inline]
+    fn join_outer(
+        &mut self,
+        point: Point,
+        offset0: Point,
+        offset1: Point,
+        p0: Point,
+        p1: Point,
+        left: bool,
+    ) {
+        if offset0 == Point::new(0.0, 0.0) || offset1 == Point::new(0.0, 0.0) {
+            self.push_edge(p0, p1, left);
+            return;
+        }
+
+        let cos = offset0.dot(offset1) / (0.25 * self.width * self.width);
+
+        match self.join {
+            LineJoin::Miter => {
+                let limit = self.miter_limit;
+                // The ratio of the miter length to the stroke width is 1/cos(θ/2), where θ is the
+                // angle between the two segments.
+                if (1.0 + cos) * limit * limit >= 2.0 {
+                    let miter =
+                        self.transform * (point + (1.0 / (1.0 + cos)) * (offset0 + offset1));
+                    self.push_edge(p0, miter, left);
+                    self.push_edge(miter, p1, left);
+                } else {
+                    self.push_edge(p0, p1, left);
+                }
+            }
+            LineJoin::Round => {
+                // Sweep from offset0 to offset1 on the outer side, passing through the direction
+                // of the incoming segment.
+                let angle = offset0.cross(offset1).abs().atan2(offset0.dot(offset1));
+                let angle = if left { angle } else { -angle };
+
+                let segments = self.arc_segments(angle);
+                let step = angle / segments as f32;
+                let (sin, cos) = step.sin_cos();
+
+                let mut vector = offset0;
+                let mut prev = p0;
+                for _ in 1..segments {
+                    vector = Point::new(
+                        cos * vector.x - sin * vector.y,
+                        sin * vector.x + cos * vector.y,
+                    );
+                    let next = self.transform * (point + vector);
+                    self.push_edge(prev, next, left);
+                    prev = next;
+                }
+                self.push_edge(prev, p1, left);
+            }
+            LineJoin::Bevel => {
+                self.push_edge(p0, p1, left);
+            }
+        }
     }
 
     #[inline]
     fn stroke_curve<C: Curve>(&mut self, curve: &C) {
         if curve.start() == curve.end() {
+            self.degenerate(curve.start());
             return;
         }
 
+        self.dot = None;
+
         let curve_transformed = curve.transform(self.transform);
 
         let segments = curve_transformed.segments_for_tolerance(TOLERANCE).clamp(1, MAX_SEGMENTS);
         let dt = 1.0 / segments as f32;
 
         let start = curve_transformed.start();
-        let start_tangent = curve.tangent(dt.min(0.5));
-        let start_normal = Point::new(-start_tangent.y, start_tangent.x);
-        let normal_len = start_normal.length();
-        let offset = if normal_len.abs() < 1e-6 {
-            Point::new(0.0, 0.0)
-        } else {
-            0.5 * self.width * (1.0 / normal_len) * start_normal
-        };
+        let offset = self.offset(curve.tangent(dt.min(0.5)));
         let offset_transformed = self.transform.linear() * offset;
         let right = start + offset_transformed;
         let left = start - offset_transformed;
 
         if self.closed {
+            self.first_point = curve.start();
+            self.first_offset = offset;
             self.first_right = right;
             self.first_left = left;
             self.closed = false;
         } else {
-            self.join(right, left);
+            self.join(curve.start(), offset, right, left);
         }
 
+        self.prev_offset = offset;
         self.prev_right = right;
         self.prev_left = left;
 
         let mut t = dt;
         for _ in 0..segments {
             let point = curve_transformed.eval(t);
-            let tangent = curve.tangent(t.min(1.0 - dt));
-            let normal = Point::new(-tangent.y, tangent.x);
-            let normal_len = normal.length();
-            let offset = if normal_len.abs() < 1e-6 {
-                Point::new(0.0, 0.0)
-            } else {
-                0.5 * self.width * (1.0 / normal_len) * normal
-            };
+            let offset = self.offset(curve.tangent(t.min(1.0 - dt)));
             let offset_transformed = self.transform.linear() * offset;
             let right = point + offset_transformed;
             let left = point - offset_transformed;
@@ -384,18 +660,31 @@ impl Stroker<'_> {
             self.lines.push(Line::new(self.prev_right, right));
             self.lines.push(Line::new(left, self.prev_left));
 
+            self.prev_offset = offset;
             self.prev_right = right;
             self.prev_left = left;
             t += dt;
         }
 
+        self.prev_point = curve.end();
         self.closed = false;
     }
 
     #[inline]
     fn close(&mut self) {
-        self.join(self.first_right, self.first_left);
-        self.closed = true;
+        if !self.closed {
+            self.join(
+                self.first_point,
+                self.first_offset,
+                self.first_right,
+                self.first_left,
+            );
+            self.closed = true;
+        }
+
+        if let Some(point) = self.dot.take() {
+            self.draw_dot(point);
+        }
     }
 
     #[inline]
@@ -405,40 +694,304 @@ impl Stroker<'_> {
             self.cap_end();
             self.closed = true;
         }
+
+        if let Some(point) = self.dot.take() {
+            self.draw_dot(point);
+        }
     }
 }
 
-#[inline]
-pub fn stroke(path: &Path, width: f32, transform: Affine, lines: &mut Vec<Line>) {
-    let mut stroker = Stroker::new(width, transform, lines);
+/// Receives the curves of a path being stroked, one subpath at a time.
+trait StrokeSink {
+    fn move_to(&mut self, point: Point);
+    fn curve<C: Curve>(&mut self, curve: &C);
+    fn degenerate(&mut self, point: Point);
+    fn close(&mut self);
+    fn finish(&mut self);
+}
+
+impl StrokeSink for Stroker<'_> {
+    #[inline]
+    fn move_to(&mut self, _point: Point) {
+        self.finish();
+    }
+
+    #[inline]
+    fn curve<C: Curve>(&mut self, curve: &C) {
+        self.stroke_curve(curve);
+    }
+
+    #[inline]
+    fn degenerate(&mut self, point: Point) {
+        Stroker::degenerate(self, point);
+    }
 
+    #[inline]
+    fn close(&mut self) {
+        Stroker::close(self);
+    }
+
+    #[inline]
+    fn finish(&mut self) {
+        Stroker::finish(self);
+    }
+}
+
+/// A position within a dash pattern.
+#[derive(Copy, Clone)]
+struct DashState {
+    index: usize,
+    remaining: f32,
+    on: bool,
+}
+
+impl DashState {
+    /// Finds the position at which the pattern starts. Returns `None` if the pattern is invalid or
+    /// has zero length, in which case the path should be stroked without dashing.
+    #[inline]
+    fn initial(dash: &Dash) -> Option<DashState> {
+        let array = &dash.array[..];
+        if array.is_empty() || array.iter().any(|&length| !(length >= 0.0 && length.is_finite())) {
+            return None;
+        }
+
+        // An odd number of lengths is repeated to produce an even number. Since entries alternate
+        // between on and off as the index advances, this only affects the length of the period.
+        let mut period = array.iter().sum::<f32>();
+        if array.len() % 2 == 1 {
+            period *= 2.0;
+        }
+        if period <= 0.0 {
+            return None;
+        }
+
+        // An offset falling exactly at the end of an entry starts at the next one, so that the
+        // subpath doesn't begin with an empty dash. Entries of zero length are still kept.
+        let mut phase = dash.offset.rem_euclid(period);
+        let mut index = 0;
+        let mut on = true;
+        while phase > array[index] || (phase == array[index] && array[index] > 0.0) {
+            phase -= array[index];
+            index = (index + 1) % array.len();
+            on = !on;
+        }
+
+        Some(DashState {
+            index,
+            remaining: array[index] - phase,
+            on,
+        })
+    }
+}
+
+/// Splits a path into dashes and passes them on to a `Stroker`.
+///
+/// Curves are split at the parameters where dashes begin and end, so that the part of a dash lying
+/// on each segment of the path is stroked as a single curve, and joins are only added at the
+/// vertices of the path. Lengths are measured along the flattened curves, in untransformed
+/// coordinates.
+struct Dasher<'a, S> {
+    array: &'a [f32],
+    initial: DashState,
+    state: DashState,
+    transform: Affine,
+    start: Point,
+    // The first dash of each subpath is buffered, since it has to be joined to the last dash if the
+    // subpath is closed.
+    first_dash: Vec<Cubic>,
+    in_first_dash: bool,
+    sink: &'a mut S,
+}
+
+impl<'a, S: StrokeSink> Dasher<'a, S> {
+    #[inline]
+    fn new(
+        array: &'a [f32],
+        initial: DashState,
+        transform: Affine,
+        sink: &'a mut S,
+    ) -> Dasher<'a, S> {
+        Dasher {
+            array,
+            initial,
+            state: initial,
+            transform,
+            start: Point::new(0.0, 0.0),
+            first_dash: Vec::new(),
+            in_first_dash: false,
+            sink,
+        }
+    }
+
+    /// Restarts the dash pattern at the beginning of a subpath.
+    #[inline]
+    fn restart(&mut self, point: Point) {
+        self.state = self.initial;
+        self.start = point;
+
+        self.first_dash.clear();
+        self.in_first_dash = self.state.on;
+    }
+
+    #[inline]
+    fn advance(&mut self) {
+        self.state.index = (self.state.index + 1) % self.array.len();
+        self.state.remaining = self.array[self.state.index];
+        self.state.on = !self.state.on;
+    }
+
+    /// Adds a curve to the current dash.
+    #[inline]
+    fn dash<C: Curve>(&mut self, curve: &C) {
+        if self.in_first_dash {
+            self.first_dash.push(curve.to_cubic());
+        } else {
+            self.sink.curve(curve);
+        }
+    }
+
+    #[inline]
+    fn end_dash(&mut self) {
+        if self.in_first_dash {
+            self.in_first_dash = false;
+        } else {
+            self.sink.finish();
+        }
+    }
+
+    /// Strokes the buffered first dash, continuing the current subpath of the stroke.
+    #[inline]
+    fn flush_first_dash(&mut self) {
+        for cubic in self.first_dash.drain(..) {
+            self.sink.curve(&cubic);
+        }
+    }
+}
+
+impl<S: StrokeSink> StrokeSink for Dasher<'_, S> {
+    #[inline]
+    fn move_to(&mut self, point: Point) {
+        self.finish();
+        self.restart(point);
+    }
+
+    #[inline]
+    fn curve<C: Curve>(&mut self, curve: &C) {
+        let segments = curve
+            .transform(self.transform)
+            .segments_for_tolerance(TOLERANCE)
+            .clamp(1, MAX_SEGMENTS);
+
+        // The parameter at which the current dash or gap begins on this curve.
+        let mut begin = 0.0;
+        let mut prev = curve.start();
+        for i in 0..segments {
+            let t0 = i as f32 / segments as f32;
+            let t1 = (i + 1) as f32 / segments as f32;
+            let point = curve.eval(t1);
+            let length = prev.distance(point);
+
+            let mut traveled = 0.0;
+            while length - traveled > self.state.remaining {
+                traveled += self.state.remaining;
+                let t = t0 + (t1 - t0) * (traveled / length);
+
+                if self.state.on {
+                    self.dash(&curve.split(begin, t));
+                    self.end_dash();
+                } else {
+                    self.sink.degenerate(curve.eval(t));
+                }
+
+                begin = t;
+                self.advance();
+            }
+
+            self.state.remaining -= length - traveled;
+            prev = point;
+        }
+
+        if self.state.on {
+            self.dash(&curve.split(begin, 1.0));
+        }
+    }
+
+    #[inline]
+    fn degenerate(&mut self, point: Point) {
+        if self.state.on {
+            self.dash(&Line::new(point, point));
+        }
+    }
+
+    #[inline]
+    fn close(&mut self) {
+        if self.in_first_dash {
+            // The entire subpath is a single dash, so it can be stroked as a closed subpath.
+            if !self.first_dash.is_empty() {
+                self.sink.degenerate(self.start);
+                self.flush_first_dash();
+                self.sink.close();
+            }
+        } else if !self.first_dash.is_empty() {
+            // Join the last dash to the first one if it reaches the end of the subpath.
+            if !self.state.on {
+                self.sink.degenerate(self.start);
+            }
+            self.flush_first_dash();
+            self.sink.finish();
+        } else if self.state.on {
+            self.sink.finish();
+        }
+
+        // Any segments after a close begin a new subpath at the same point.
+        self.restart(self.start);
+    }
+
+    #[inline]
+    fn finish(&mut self) {
+        if self.state.on && !self.in_first_dash {
+            self.sink.finish();
+        }
+
+        if !self.first_dash.is_empty() {
+            self.sink.degenerate(self.start);
+            self.flush_first_dash();
+            self.sink.finish();
+        }
+
+        self.in_first_dash = false;
+    }
+}
+
+#[inline]
+fn stroke_with<S: StrokeSink>(path: &Path, sink: &mut S) {
     let mut points = path.points.iter();
     let mut first = Point::new(0.0, 0.0);
     let mut prev = Point::new(0.0, 0.0);
     for verb in &path.verbs {
         match *verb {
             Verb::Move => {
-                stroker.finish();
-
                 first = *points.next().unwrap();
                 prev = first;
+
+                sink.move_to(first);
             }
             Verb::Line => {
                 let p1 = *points.next().unwrap();
-                stroker.stroke_curve(&Line { p0: prev, p1 });
+                sink.curve(&Line { p0: prev, p1 });
                 prev = p1;
             }
             Verb::Quadratic => {
                 let p1 = *points.next().unwrap();
                 let p2 = *points.next().unwrap();
-                stroker.stroke_curve(&Quadratic { p0: prev, p1, p2 });
+                sink.curve(&Quadratic { p0: prev, p1, p2 });
                 prev = p2;
             }
             Verb::Cubic => {
                 let p1 = *points.next().unwrap();
                 let p2 = *points.next().unwrap();
                 let p3 = *points.next().unwrap();
-                stroker.stroke_curve(&Cubic {
+                sink.curve(&Cubic {
                     p0: prev,
                     p1,
                     p2,
@@ -448,17 +1001,284 @@ pub fn stroke(path: &Path, width: f32, transform: Affine, lines: &mut Vec<Line>)
             }
             Verb::Close => {
                 if prev != first {
-                    stroker.stroke_curve(&Line {
+                    sink.curve(&Line {
                         p0: prev,
                         p1: first,
                     });
+                } else {
+                    sink.degenerate(first);
                 }
                 prev = first;
 
-                stroker.close();
+                sink.close();
             }
         }
     }
 
-    stroker.finish();
+    sink.finish();
+}
+
+/// Computes the total length of the control polygons of a path, which is at least its length.
+#[inline]
+fn polygon_length(path: &Path) -> f32 {
+    let mut points = path.points.iter();
+    let mut length = 0.0;
+    let mut first = Point::new(0.0, 0.0);
+    let mut prev = Point::new(0.0, 0.0);
+    for verb in &path.verbs {
+        let count = match *verb {
+            Verb::Move => {
+                first = *points.next().unwrap();
+                prev = first;
+                continue;
+            }
+            Verb::Line => 1,
+            Verb::Quadratic => 2,
+            Verb::Cubic => 3,
+            Verb::Close => {
+                length += prev.distance(first);
+                prev = first;
+                continue;
+            }
+        };
+
+        for &point in points.by_ref().take(count) {
+            length += prev.distance(point);
+            prev = point;
+        }
+    }
+
+    length
+}
+
+#[inline]
+pub fn stroke(path: &Path, style: &StrokeStyle, transform: Affine, lines: &mut Vec<Line>) {
+    let mut stroker = Stroker::new(style, transform, lines);
+
+    if let Some(ref dash) = style.dash {
+        let period = dash.array.iter().sum::<f32>();
+        let dashes = polygon_length(path) / period * dash.array.len() as f32;
+        let initial = DashState::initial(dash).filter(|_| dashes <= MAX_DASHES);
+        if let Some(initial) = initial {
+            let mut dasher = Dasher::new(&dash.array, initial, transform, &mut stroker);
+            stroke_with(path, &mut dasher);
+            return;
+        }
+    }
+
+    stroke_with(path, &mut stroker);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Curve(Point, Point),
+        Degenerate(Point),
+        Close,
+        Finish,
+    }
+
+    /// Records the calls made by a `Dasher`, with each curve reduced to its endpoints.
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<Event>,
+    }
+
+    impl StrokeSink for Recorder {
+        fn move_to(&mut self, _point: Point) {}
+
+        fn curve<C: Curve>(&mut self, curve: &C) {
+            self.events.push(Event::Curve(curve.start(), curve.end()));
+        }
+
+        fn degenerate(&mut self, point: Point) {
+            self.events.push(Event::Degenerate(point));
+        }
+
+        fn close(&mut self) {
+            self.events.push(Event::Close);
+        }
+
+        fn finish(&mut self) {
+            self.events.push(Event::Finish);
+        }
+    }
+
+    fn dash(array: &[f32], offset: f32) -> Dash {
+        Dash {
+            array: array.to_vec(),
+            offset,
+        }
+    }
+
+    fn initial(array: &[f32], offset: f32) -> Option<(usize, f32, bool)> {
+        DashState::initial(&dash(array, offset))
+            .map(|state| (state.index, state.remaining, state.on))
+    }
+
+    /// Dashes a path with a pattern. Like a `Stroker`, the `Dasher` finishes the previous subpath
+    /// when a new one begins, so the events always start with `Finish`.
+    fn record(path: &Path, array: &[f32], offset: f32) -> Vec<Event> {
+        let mut recorder = Recorder::default();
+        let initial = DashState::initial(&dash(array, offset)).unwrap();
+        let mut dasher = Dasher::new(array, initial, Affine::id(), &mut recorder);
+        stroke_with(path, &mut dasher);
+        recorder.events
+    }
+
+    fn p(x: f32, y: f32) -> Point {
+        Point::new(x, y)
+    }
+
+    fn square() -> Path {
+        let mut path = Path::new();
+        path.move_to(p(0.0, 0.0))
+            .line_to(p(4.0, 0.0))
+            .line_to(p(4.0, 4.0))
+            .line_to(p(0.0, 4.0))
+            .close();
+        path
+    }
+
+    #[test]
+    fn initial_offset() {
+        assert_eq!(initial(&[2.0, 1.0], 0.0), Some((0, 2.0, true)));
+        assert_eq!(initial(&[2.0, 1.0], 2.5), Some((1, 0.5, false)));
+        // Offsets wrap around the period in both directions.
+        assert_eq!(initial(&[2.0, 1.0], 5.5), Some((1, 0.5, false)));
+        assert_eq!(initial(&[2.0, 1.0], -0.5), Some((1, 0.5, false)));
+        // An odd number of lengths is repeated, so the second pass through the array swaps on and
+        // off.
+        assert_eq!(initial(&[1.0, 2.0, 3.0], 6.5), Some((0, 0.5, false)));
+        assert_eq!(initial(&[1.0, 2.0, 3.0], 9.0), Some((2, 3.0, false)));
+        // An offset at the end of an entry starts at the following one, unless it has zero length.
+        assert_eq!(initial(&[5.0, 5.0], 5.0), Some((1, 5.0, false)));
+        assert_eq!(initial(&[0.0, 5.0], 5.0), Some((0, 0.0, true)));
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        assert_eq!(initial(&[], 0.0), None);
+        assert_eq!(initial(&[0.0, 0.0], 0.0), None);
+        assert_eq!(initial(&[1.0, -1.0], 0.0), None);
+        assert_eq!(initial(&[1.0, f32::NAN], 0.0), None);
+        assert_eq!(initial(&[1.0, f32::INFINITY], 0.0), None);
+    }
+
+    #[test]
+    fn too_many_dashes_fall_back_to_solid_stroke() {
+        let mut path = Path::new();
+        path.move_to(p(0.0, 0.0)).line_to(p(1e6, 0.0));
+        let solid = StrokeStyle::new(1.0);
+        let mut dashed = solid.clone();
+
+        // Adding up lengths this small along the line would stop making progress, so the line is
+        // stroked without dashing.
+        dashed.dash = Some(dash(&[1e-3, 1e-3], 0.0));
+        let mut lines = Vec::new();
+        stroke(&path, &dashed, Affine::id(), &mut lines);
+        let mut expected = Vec::new();
+        stroke(&path, &solid, Affine::id(), &mut expected);
+        let endpoints = |lines: &[Line]| lines.iter().map(|l| (l.p0, l.p1)).collect::<Vec<_>>();
+        assert_eq!(endpoints(&lines), endpoints(&expected));
+    }
+
+    #[test]
+    fn dash_spans_vertices() {
+        let mut path = Path::new();
+        path.move_to(p(0.0, 0.0)).line_to(p(4.0, 0.0)).line_to(p(4.0, 4.0));
+
+        // The second dash covers the corner, so it is passed on as two curves that the stroker
+        // joins, rather than being split into two dashes.
+        assert_eq!(
+            record(&path, &[2.0, 1.5], 0.0),
+            [
+                Event::Finish,
+                Event::Degenerate(p(3.5, 0.0)),
+                Event::Curve(p(3.5, 0.0), p(4.0, 0.0)),
+                Event::Curve(p(4.0, 0.0), p(4.0, 1.5)),
+                Event::Finish,
+                Event::Degenerate(p(4.0, 3.0)),
+                Event::Curve(p(4.0, 3.0), p(4.0, 4.0)),
+                Event::Finish,
+                Event::Degenerate(p(0.0, 0.0)),
+                Event::Curve(p(0.0, 0.0), p(2.0, 0.0)),
+                Event::Finish,
+            ]
+        );
+    }
+
+    #[test]
+    fn curve_dash_is_one_curve() {
+        let mut path = Path::new();
+        path.move_to(p(0.0, 0.0)).cubic_to(p(0.0, 50.0), p(100.0, 50.0), p(100.0, 0.0));
+
+        // A dash lying within a single curve is passed on as one curve, however finely the curve
+        // is flattened to measure it.
+        let events = record(&path, &[1000.0, 1.0], 0.0);
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[1], Event::Degenerate(p(0.0, 0.0)));
+        assert_eq!(events[2], Event::Curve(p(0.0, 0.0), p(100.0, 0.0)));
+        assert_eq!(events[3], Event::Finish);
+    }
+
+    #[test]
+    fn open_subpath_buffers_first_dash_until_finish() {
+        let mut path = Path::new();
+        path.move_to(p(0.0, 0.0)).line_to(p(10.0, 0.0));
+
+        assert_eq!(
+            record(&path, &[3.0, 2.0], 0.0),
+            [
+                Event::Finish,
+                Event::Degenerate(p(5.0, 0.0)),
+                Event::Curve(p(5.0, 0.0), p(8.0, 0.0)),
+                Event::Finish,
+                Event::Degenerate(p(0.0, 0.0)),
+                Event::Curve(p(0.0, 0.0), p(3.0, 0.0)),
+                Event::Finish,
+            ]
+        );
+    }
+
+    #[test]
+    fn closed_subpath_joins_last_dash_to_first() {
+        // The last dash runs from 15 to the end of the perimeter at 16, and continues into the
+        // buffered first dash from 0 to 5.
+        assert_eq!(
+            record(&square(), &[5.0, 2.5], 0.0),
+            [
+                Event::Finish,
+                Event::Degenerate(p(4.0, 3.5)),
+                Event::Curve(p(4.0, 3.5), p(4.0, 4.0)),
+                Event::Curve(p(4.0, 4.0), p(0.0, 4.0)),
+                Event::Curve(p(0.0, 4.0), p(0.0, 3.5)),
+                Event::Finish,
+                Event::Degenerate(p(0.0, 1.0)),
+                Event::Curve(p(0.0, 1.0), p(0.0, 0.0)),
+                Event::Curve(p(0.0, 0.0), p(4.0, 0.0)),
+                Event::Curve(p(4.0, 0.0), p(4.0, 1.0)),
+                Event::Finish,
+            ]
+        );
+    }
+
+    #[test]
+    fn closed_subpath_within_one_dash() {
+        assert_eq!(
+            record(&square(), &[20.0, 1.0], 0.0),
+            [
+                Event::Finish,
+                Event::Degenerate(p(0.0, 0.0)),
+                Event::Curve(p(0.0, 0.0), p(4.0, 0.0)),
+                Event::Curve(p(4.0, 0.0), p(4.0, 4.0)),
+                Event::Curve(p(4.0, 4.0), p(0.0, 4.0)),
+                Event::Curve(p(0.0, 4.0), p(0.0, 0.0)),
+                Event::Close,
+            ]
+        );
+    }
 }
@@ -203,6 +203,27 @@ impl Affine {
     pub fn linear(&self) -> Affine {
         Affine([self.0[0], self.0[1], 0.0, self.0[3], self.0[4], 0.0])
     }
+
+    /// Computes the inverse of the transformation, or `None` if it is not invertible.
+    #[inline]
+    pub fn invert(&self) -> Option<Affine> {
+        let [a, b, c, d, e, f] = self.0;
+
+        let det = a * e - b * d;
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+
+        Some(Affine([
+            inv_det * e,
+            -inv_det * b,
+            inv_det * (b * f - c * e),
+            -inv_det * d,
+            inv_det * a,
+            inv_det * (c * d - a * f),
+        ]))
+    }
 }
 
 impl ops::Mul<Affine> for Affine {
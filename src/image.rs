@@ -0,0 +1,70 @@
+use crate::color::PremulColor;
+
+/// A borrowed image of premultiplied ARGB pixels, in the same format as a `RenderTarget`.
+#[derive(Copy, Clone, Debug)]
+pub struct Image<'a> {
+    data: &'a [u32],
+    width: usize,
+    height: usize,
+    stride: usize,
+}
+
+impl<'a> Image<'a> {
+    /// Wraps `height` rows of `width` pixels, where consecutive rows start `stride` pixels apart.
+    ///
+    /// Panics if `data` is too small to hold the image or if `stride` is less than `width`.
+    #[inline]
+    pub fn new(data: &'a [u32], width: usize, height: usize, stride: usize) -> Image<'a> {
+        assert!(stride >= width);
+        assert!(height == 0 || data.len() >= (height - 1) * stride + width);
+
+        Image {
+            data,
+            width,
+            height,
+            stride,
+        }
+    }
+
+    /// Like `new`, but for typed premultiplied colors.
+    #[inline]
+    pub fn from_colors(
+        data: &'a [PremulColor],
+        width: usize,
+        height: usize,
+        stride: usize,
+    ) -> Image<'a> {
+        Image::new(PremulColor::as_argb(data), width, height, stride)
+    }
+
+    #[inline]
+    pub fn data(&self) -> &'a [u32] {
+        self.data
+    }
+
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    #[inline]
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+}
+
+/// Determines how an image is sampled at positions between pixel centers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Filter {
+    /// Uses the pixel containing the position.
+    Nearest,
+    /// Interpolates linearly between the four nearest pixels.
+    Bilinear,
+    /// Interpolates between the sixteen nearest pixels with a cubic (Mitchell-Netravali) filter.
+    Bicubic,
+}
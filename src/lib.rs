@@ -1,15 +1,28 @@
 mod bbox;
+mod blend;
+mod blur;
 mod color;
 mod flatten;
 mod geom;
+mod image;
+mod paint;
 mod path;
 mod pipeline;
+mod pixels;
 mod raster;
 mod render;
+mod stroke;
 mod text;
 
+pub use bbox::Bbox;
+pub use blend::*;
 pub use color::*;
 pub use geom::*;
-pub use path::Path;
+pub use image::*;
+pub use paint::*;
+pub use path::{FillRule, Path};
+pub use pipeline::Backend;
+pub use pixels::*;
 pub use render::*;
+pub use stroke::*;
 pub use text::*;
@@ -0,0 +1,185 @@
+use crate::color::{Color, ColorF32};
+use crate::geom::{Affine, Point};
+use crate::image::{Filter, Image};
+
+/// The source of color for a fill or stroke.
+#[derive(Copy, Clone, Debug)]
+pub enum Paint<'a> {
+    /// A single solid color.
+    Color(Color),
+    /// A single solid color with float channels, which may exceed 1 on float render targets.
+    ColorF32(ColorF32),
+    /// A gradient between multiple colors.
+    Gradient(&'a Gradient),
+    /// An image.
+    Pattern(Pattern<'a>),
+}
+
+impl From<Color> for Paint<'_> {
+    #[inline]
+    fn from(color: Color) -> Self {
+        Paint::Color(color)
+    }
+}
+
+impl From<ColorF32> for Paint<'_> {
+    #[inline]
+    fn from(color: ColorF32) -> Self {
+        Paint::ColorF32(color)
+    }
+}
+
+impl<'a> From<&'a Gradient> for Paint<'a> {
+    #[inline]
+    fn from(gradient: &'a Gradient) -> Self {
+        Paint::Gradient(gradient)
+    }
+}
+
+impl<'a> From<Pattern<'a>> for Paint<'a> {
+    #[inline]
+    fn from(pattern: Pattern<'a>) -> Self {
+        Paint::Pattern(pattern)
+    }
+}
+
+/// Determines how a paint is extended outside of its defined area.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Extend {
+    /// Extends the colors at the edges.
+    Pad,
+    /// Repeats the paint.
+    Repeat,
+    /// Repeats the paint, mirroring every other repetition.
+    Reflect,
+}
+
+/// A color at a given position along a gradient.
+#[derive(Copy, Clone, Debug)]
+pub struct Stop {
+    /// The position of the stop, between 0 and 1.
+    pub offset: f32,
+    pub color: ColorF32,
+}
+
+impl Stop {
+    /// Constructs a stop with either a `Color` or a `ColorF32`.
+    #[inline]
+    pub fn new(offset: f32, color: impl Into<ColorF32>) -> Stop {
+        Stop {
+            offset,
+            color: color.into(),
+        }
+    }
+}
+
+/// The geometry of a gradient.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GradientKind {
+    /// Varies along the line from `start` to `end`, and is constant along lines perpendicular to it.
+    Linear { start: Point, end: Point },
+    /// Varies along circles which are interpolated between a point at `focal` (at offset 0) and
+    /// the circle with the given `center` and `radius` (at offset 1).
+    ///
+    /// A focal point further than 0.99 times the radius from the center is moved towards the
+    /// center until it is at that distance, so that it lies strictly inside the circle. The focal
+    /// point is always a single point: two-point conical gradients, where the start circle has a
+    /// nonzero radius, are not supported.
+    Radial {
+        center: Point,
+        radius: f32,
+        focal: Point,
+    },
+    /// Varies with the angle around `center`, from `start_angle` (at offset 0) to `end_angle` (at
+    /// offset 1). Angles are in radians, measured from the positive x axis towards the positive y
+    /// axis.
+    Sweep {
+        center: Point,
+        start_angle: f32,
+        end_angle: f32,
+    },
+}
+
+/// A gradient paint.
+///
+/// Colors are interpolated between stops in non-premultiplied form. Stops are expected to be in
+/// order of increasing offset; a stop whose offset is smaller than that of a previous stop is
+/// treated as having the same offset as the previous stop.
+#[derive(Clone, Debug)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    pub stops: Vec<Stop>,
+    pub extend: Extend,
+    /// A transformation applied to the gradient geometry, on top of the transformation of the path
+    /// being drawn.
+    pub transform: Affine,
+}
+
+impl Gradient {
+    /// Constructs a linear gradient with the `Pad` extend mode and an identity transformation.
+    #[inline]
+    pub fn linear(start: Point, end: Point, stops: Vec<Stop>) -> Gradient {
+        Gradient {
+            kind: GradientKind::Linear { start, end },
+            stops,
+            extend: Extend::Pad,
+            transform: Affine::id(),
+        }
+    }
+
+    /// Constructs a radial gradient with the `Pad` extend mode and an identity transformation. If
+    /// no focal point is given, it is placed at the center.
+    #[inline]
+    pub fn radial(center: Point, radius: f32, focal: Option<Point>, stops: Vec<Stop>) -> Gradient {
+        Gradient {
+            kind: GradientKind::Radial {
+                center,
+                radius,
+                focal: focal.unwrap_or(center),
+            },
+            stops,
+            extend: Extend::Pad,
+            transform: Affine::id(),
+        }
+    }
+
+    /// Constructs a sweep gradient with the `Pad` extend mode and an identity transformation.
+    #[inline]
+    pub fn sweep(center: Point, start_angle: f32, end_angle: f32, stops: Vec<Stop>) -> Gradient {
+        Gradient {
+            kind: GradientKind::Sweep {
+                center,
+                start_angle,
+                end_angle,
+            },
+            stops,
+            extend: Extend::Pad,
+            transform: Affine::id(),
+        }
+    }
+}
+
+/// An image paint.
+#[derive(Copy, Clone, Debug)]
+pub struct Pattern<'a> {
+    pub image: Image<'a>,
+    /// A transformation from image coordinates, where each pixel is a unit square, to the
+    /// coordinate space of the path being drawn.
+    pub transform: Affine,
+    pub extend: Extend,
+    pub filter: Filter,
+}
+
+impl<'a> Pattern<'a> {
+    /// Constructs a pattern with an identity transformation, the `Pad` extend mode and bilinear
+    /// filtering.
+    #[inline]
+    pub fn new(image: Image<'a>) -> Pattern<'a> {
+        Pattern {
+            image,
+            transform: Affine::id(),
+            extend: Extend::Pad,
+            filter: Filter::Bilinear,
+        }
+    }
+}
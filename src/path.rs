@@ -24,6 +24,15 @@ pub enum Command {
     Close,
 }
 
+/// The rule used to determine which regions of a path are inside the path.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FillRule {
+    /// A point is inside the path if the winding number of the path around it is nonzero.
+    NonZero,
+    /// A point is inside the path if the winding number of the path around it is odd.
+    EvenOdd,
+}
+
 impl Path {
     #[inline]
     pub fn new() -> Path {
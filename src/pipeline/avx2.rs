@@ -3,184 +3,294 @@ use std::arch::x86::*;
 #[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::*;
 
-use super::Pipeline;
-use crate::Color;
-
-pub struct Avx2 {
-    color: Color,
-    a: __m256,
-    r: __m256,
-    g: __m256,
-    b: __m256,
-    accum: f32,
-    cvg: f32,
-}
+use std::ops::{Add, Div, Mul, Sub};
+
+use super::{map4, Simd};
+
+#[derive(Copy, Clone)]
+pub struct Avx2(__m256);
+
+impl Simd for Avx2 {
+    const LANES: usize = 8;
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn vectorize<R>(f: impl FnOnce() -> R) -> R {
+        f()
+    }
+
+    #[inline(always)]
+    fn splat(value: f32) -> Self {
+        unsafe { Avx2(_mm256_set1_ps(value)) }
+    }
 
-impl Avx2 {
     #[inline(always)]
-    fn accum(&mut self, deltas: __m256) -> __m256 {
+    fn ramp() -> Self {
+        unsafe { Avx2(_mm256_setr_ps(0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0)) }
+    }
+
+    #[inline(always)]
+    fn load(src: &[f32]) -> Self {
+        assert!(src.len() >= Self::LANES);
+        unsafe { Avx2(_mm256_loadu_ps(src.as_ptr())) }
+    }
+
+    #[inline(always)]
+    fn store(self, dst: &mut [f32]) {
+        assert!(dst.len() >= Self::LANES);
+        unsafe { _mm256_storeu_ps(dst.as_mut_ptr(), self.0) }
+    }
+
+    #[inline(always)]
+    fn min(self, other: Self) -> Self {
+        unsafe { Avx2(_mm256_min_ps(self.0, other.0)) }
+    }
+
+    #[inline(always)]
+    fn max(self, other: Self) -> Self {
+        unsafe { Avx2(_mm256_max_ps(self.0, other.0)) }
+    }
+
+    #[inline(always)]
+    fn abs(self) -> Self {
         unsafe {
-            let shifted = _mm256_castsi256_ps(_mm256_slli_si256(_mm256_castps_si256(deltas), 4));
-            let sum1 = _mm256_add_ps(deltas, shifted);
+            let mask = _mm256_castsi256_ps(_mm256_set1_epi32(!(1 << 31)));
+            Avx2(_mm256_and_ps(self.0, mask))
+        }
+    }
+
+    #[inline(always)]
+    fn floor(self) -> Self {
+        unsafe {
+            Avx2(_mm256_round_ps(
+                self.0,
+                _MM_FROUND_TO_NEG_INF | _MM_FROUND_NO_EXC,
+            ))
+        }
+    }
+
+    #[inline(always)]
+    fn round(self) -> Self {
+        unsafe {
+            Avx2(_mm256_round_ps(
+                self.0,
+                _MM_FROUND_TO_NEAREST_INT | _MM_FROUND_NO_EXC,
+            ))
+        }
+    }
+
+    #[inline(always)]
+    fn sqrt(self) -> Self {
+        unsafe { Avx2(_mm256_sqrt_ps(self.0)) }
+    }
+
+    #[inline(always)]
+    fn lt(self, other: Self) -> Self {
+        unsafe { Avx2(_mm256_cmp_ps(self.0, other.0, _CMP_LT_OQ)) }
+    }
+
+    #[inline(always)]
+    fn select(mask: Self, if_true: Self, if_false: Self) -> Self {
+        unsafe { Avx2(_mm256_blendv_ps(if_false.0, if_true.0, mask.0)) }
+    }
+
+    #[inline(always)]
+    fn prefix_sum(self) -> Self {
+        unsafe {
+            let shifted = _mm256_castsi256_ps(_mm256_slli_si256(_mm256_castps_si256(self.0), 4));
+            let sum1 = _mm256_add_ps(self.0, shifted);
             let shifted = _mm256_castsi256_ps(_mm256_slli_si256(_mm256_castps_si256(sum1), 8));
             let sum2 = _mm256_add_ps(sum1, shifted);
             let lower = _mm256_castps256_ps128(sum2);
             let total = _mm_shuffle_ps(lower, lower, 0xFF);
             let carry = _mm256_insertf128_ps(_mm256_setzero_ps(), total, 1);
-            let sum3 = _mm256_add_ps(sum2, carry);
-            let sum = _mm256_add_ps(_mm256_set1_ps(self.accum), sum3);
-
-            let abs = _mm256_and_ps(sum, _mm256_castsi256_ps(_mm256_set1_epi32(!(1 << 31))));
-            let mask = _mm256_min_ps(abs, _mm256_set1_ps(1.0));
-
-            let sum_upper = _mm256_extractf128_ps(sum, 1);
-            self.accum = _mm_cvtss_f32(_mm_shuffle_ps(sum_upper, sum_upper, 0x03));
-            let mask_upper = _mm256_extractf128_ps(mask, 1);
-            self.cvg = _mm_cvtss_f32(_mm_shuffle_ps(mask_upper, mask_upper, 0x03));
+            Avx2(_mm256_add_ps(sum2, carry))
+        }
+    }
 
-            mask
+    #[inline(always)]
+    fn last(self) -> f32 {
+        unsafe {
+            let upper = _mm256_extractf128_ps(self.0, 1);
+            _mm_cvtss_f32(_mm_shuffle_ps(upper, upper, 0xFF))
         }
     }
 
     #[inline(always)]
-    fn blend(&self, dst: __m256i, mask: __m256) -> __m256i {
+    fn gather(table: &[f32], index: Self) -> Self {
         unsafe {
-            let byte_mask = _mm256_set1_epi32(0xFF);
-            let a_dst = _mm256_cvtepi32_ps(_mm256_srli_epi32(dst, 24));
-            let r_dst = _mm256_cvtepi32_ps(_mm256_and_si256(_mm256_srli_epi32(dst, 16), byte_mask));
-            let g_dst = _mm256_cvtepi32_ps(_mm256_and_si256(_mm256_srli_epi32(dst, 8), byte_mask));
-            let b_dst = _mm256_cvtepi32_ps(_mm256_and_si256(dst, byte_mask));
-
-            let a_unit = _mm256_mul_ps(self.a, _mm256_set1_ps(1.0 / 255.0));
-            let inv_a = _mm256_sub_ps(_mm256_set1_ps(1.0), _mm256_mul_ps(mask, a_unit));
-            let a_out = _mm256_add_ps(_mm256_mul_ps(self.a, mask), _mm256_mul_ps(inv_a, a_dst));
-            let r_out = _mm256_add_ps(_mm256_mul_ps(self.r, mask), _mm256_mul_ps(inv_a, r_dst));
-            let g_out = _mm256_add_ps(_mm256_mul_ps(self.g, mask), _mm256_mul_ps(inv_a, g_dst));
-            let b_out = _mm256_add_ps(_mm256_mul_ps(self.b, mask), _mm256_mul_ps(inv_a, b_dst));
-
-            let out = _mm256_slli_epi32(_mm256_cvtps_epi32(a_out), 24);
-            let out = _mm256_or_si256(out, _mm256_slli_epi32(_mm256_cvtps_epi32(r_out), 16));
-            let out = _mm256_or_si256(out, _mm256_slli_epi32(_mm256_cvtps_epi32(g_out), 8));
-            let out = _mm256_or_si256(out, _mm256_cvtps_epi32(b_out));
-
-            out
+            let max = _mm256_set1_ps((table.len() - 1) as f32);
+            let clamped = _mm256_min_ps(_mm256_max_ps(index.0, _mm256_setzero_ps()), max);
+            let indices = _mm256_cvttps_epi32(clamped);
+            Avx2(_mm256_i32gather_ps(table.as_ptr(), indices, 4))
         }
     }
-}
 
-impl Pipeline for Avx2 {
     #[inline(always)]
-    fn build(color: Color) -> Self {
+    fn gather_pixels(pixels: &[u32], stride: usize, x: Self, y: Self) -> [Self; 4] {
+        assert!(!pixels.is_empty() && pixels.len() <= i32::MAX as usize);
         unsafe {
-            let a_unit = color.a() as f32 * (1.0 / 255.0);
-
-            Avx2 {
-                color,
-                a: _mm256_set1_ps(color.a() as f32),
-                r: _mm256_set1_ps(a_unit * color.r() as f32),
-                g: _mm256_set1_ps(a_unit * color.g() as f32),
-                b: _mm256_set1_ps(a_unit * color.b() as f32),
-                accum: 0.0,
-                cvg: 0.0,
-            }
+            let x = _mm256_cvttps_epi32(x.0);
+            let y = _mm256_cvttps_epi32(y.0);
+            let index =
+                _mm256_add_epi32(_mm256_mullo_epi32(y, _mm256_set1_epi32(stride as i32)), x);
+
+            // Clamp the indices so that the gather can never read out of bounds.
+            let max = _mm256_set1_epi32(pixels.len() as i32 - 1);
+            let index = _mm256_max_epi32(_mm256_min_epi32(index, max), _mm256_setzero_si256());
+            unpack(_mm256_i32gather_epi32(
+                pixels.as_ptr() as *const i32,
+                index,
+                4,
+            ))
         }
     }
 
     #[inline(always)]
-    fn reset(&mut self) {
-        self.accum = 0.0;
-        self.cvg = 0.0;
+    fn unpack(src: &[u32]) -> [Self; 4] {
+        assert!(src.len() >= Self::LANES);
+        unsafe { unpack(_mm256_loadu_si256(src.as_ptr() as *const __m256i)) }
     }
 
     #[inline(always)]
-    fn fill(&mut self, dst: &mut [u32]) {
-        if self.cvg > 254.5 / 255.0 && self.color.a() == 255 {
-            dst.fill(self.color.into());
-        } else if self.cvg > 0.5 / 255.0 {
-            unsafe {
-                let mut dst_chunks = dst.chunks_exact_mut(8);
+    fn pack(channels: [Self; 4], dst: &mut [u32]) {
+        assert!(dst.len() >= Self::LANES);
+        unsafe { _mm256_storeu_si256(dst.as_mut_ptr() as *mut __m256i, pack(channels)) }
+    }
 
-                for dst_chunk in &mut dst_chunks {
-                    let mask = _mm256_set1_ps(self.cvg);
-                    let dst = _mm256_loadu_si256(dst_chunk.as_ptr() as *const __m256i);
-                    let out = self.blend(dst, mask);
-                    _mm256_storeu_si256(dst_chunk.as_mut_ptr() as *mut __m256i, out);
-                }
+    #[inline(always)]
+    fn unpack_bytes(src: &[[u8; 4]]) -> [Self; 4] {
+        assert!(src.len() >= Self::LANES);
+        unsafe { unpack(_mm256_loadu_si256(src.as_ptr() as *const __m256i)) }
+    }
 
-                let mut dst_rem = dst_chunks.into_remainder();
+    #[inline(always)]
+    fn pack_bytes(channels: [Self; 4], dst: &mut [[u8; 4]]) {
+        assert!(dst.len() >= Self::LANES);
+        unsafe { _mm256_storeu_si256(dst.as_mut_ptr() as *mut __m256i, pack(channels)) }
+    }
 
-                if dst_rem.len() >= 4 {
-                    let mask = _mm256_set1_ps(self.cvg);
-                    let dst128 = _mm_loadu_si128(dst_rem.as_ptr() as *const __m128i);
-                    let dst = _mm256_castsi128_si256(dst128);
-                    let out = self.blend(dst, mask);
-                    let out128 = _mm256_castsi256_si128(out);
-                    _mm_storeu_si128(dst_rem.as_mut_ptr() as *mut __m128i, out128);
+    #[inline(always)]
+    fn unpack_bytes_partial(src: &[[u8; 4]]) -> [Self; 4] {
+        assert!(src.len() <= Self::LANES);
+        unsafe {
+            let mask = tail_mask(src.len());
+            unpack(_mm256_maskload_epi32(src.as_ptr() as *const i32, mask))
+        }
+    }
 
-                    dst_rem = &mut dst_rem[4..];
-                }
+    #[inline(always)]
+    fn pack_bytes_partial(channels: [Self; 4], dst: &mut [[u8; 4]]) {
+        assert!(dst.len() <= Self::LANES);
+        unsafe {
+            let mask = tail_mask(dst.len());
+            _mm256_maskstore_epi32(dst.as_mut_ptr() as *mut i32, mask, pack(channels));
+        }
+    }
 
-                for pixel in dst_rem {
-                    let mask = _mm256_set1_ps(self.cvg);
-                    let dst128 = _mm_loadu_si32(pixel as *const u32 as *const u8);
-                    let dst = _mm256_castsi128_si256(dst128);
-                    let out = self.blend(dst, mask);
-                    let out128 = _mm256_castsi256_si128(out);
-                    _mm_storeu_si32(pixel as *mut u32 as *mut u8, out128);
-                }
-            }
+    #[inline(always)]
+    fn unpack_u8(src: &[u8]) -> Self {
+        assert!(src.len() >= Self::LANES);
+        unsafe {
+            let bytes = _mm_loadl_epi64(src.as_ptr() as *const __m128i);
+            Avx2(_mm256_cvtepi32_ps(_mm256_cvtepu8_epi32(bytes)))
         }
     }
 
     #[inline(always)]
-    fn fill_edge(&mut self, dst: &mut [u32], cvg: &mut [f32]) {
-        let mut dst_chunks = dst.chunks_exact_mut(8);
-        let mut cvg_chunks = cvg.chunks_exact_mut(8);
+    fn load_partial(src: &[f32]) -> Self {
+        assert!(src.len() <= Self::LANES);
+        unsafe { Avx2(_mm256_maskload_ps(src.as_ptr(), tail_mask(src.len()))) }
+    }
 
-        for (dst_chunk, cvg_chunk) in (&mut dst_chunks).zip(&mut cvg_chunks) {
-            unsafe {
-                let deltas = _mm256_loadu_ps(cvg_chunk.as_ptr());
-                let mask = self.accum(deltas);
-                _mm256_storeu_ps(cvg_chunk.as_mut_ptr(), _mm256_setzero_ps());
+    #[inline(always)]
+    fn store_partial(self, dst: &mut [f32]) {
+        assert!(dst.len() <= Self::LANES);
+        unsafe { _mm256_maskstore_ps(dst.as_mut_ptr(), tail_mask(dst.len()), self.0) }
+    }
 
-                let dst = _mm256_loadu_si256(dst_chunk.as_ptr() as *const __m256i);
-                let out = self.blend(dst, mask);
-                _mm256_storeu_si256(dst_chunk.as_mut_ptr() as *mut __m256i, out);
-            }
+    #[inline(always)]
+    fn unpack_partial(src: &[u32]) -> [Self; 4] {
+        assert!(src.len() <= Self::LANES);
+        unsafe {
+            let mask = tail_mask(src.len());
+            unpack(_mm256_maskload_epi32(src.as_ptr() as *const i32, mask))
         }
+    }
 
-        let mut dst_rem = dst_chunks.into_remainder();
-        let mut cvg_rem = cvg_chunks.into_remainder();
+    #[inline(always)]
+    fn pack_partial(channels: [Self; 4], dst: &mut [u32]) {
+        assert!(dst.len() <= Self::LANES);
+        unsafe {
+            let mask = tail_mask(dst.len());
+            _mm256_maskstore_epi32(dst.as_mut_ptr() as *mut i32, mask, pack(channels));
+        }
+    }
+}
 
-        if dst_rem.len() >= 4 && cvg_rem.len() >= 4 {
-            unsafe {
-                let deltas = _mm256_castps128_ps256(_mm_loadu_ps(cvg_rem.as_ptr()));
-                let mask = self.accum(deltas);
-                _mm_storeu_ps(cvg_rem.as_mut_ptr(), _mm_setzero_ps());
+/// Returns a mask selecting the first `len` lanes, for use with masked loads and stores.
+#[inline(always)]
+unsafe fn tail_mask(len: usize) -> __m256i {
+    let lanes = _mm256_setr_epi32(0, 1, 2, 3, 4, 5, 6, 7);
+    _mm256_cmpgt_epi32(_mm256_set1_epi32(len as i32), lanes)
+}
 
-                let dst128 = _mm_loadu_si128(dst_rem.as_ptr() as *const __m128i);
-                let dst = _mm256_castsi128_si256(dst128);
-                let out = self.blend(dst, mask);
-                let out128 = _mm256_castsi256_si128(out);
-                _mm_storeu_si128(dst_rem.as_mut_ptr() as *mut __m128i, out128);
-            }
+#[inline(always)]
+unsafe fn unpack(pixels: __m256i) -> [Avx2; 4] {
+    let byte_mask = _mm256_set1_epi32(0xFF);
+    let unpack = |channel: __m256i| Avx2(_mm256_cvtepi32_ps(channel));
 
-            dst_rem = &mut dst_rem[4..];
-            cvg_rem = &mut cvg_rem[4..];
-        }
+    [
+        unpack(_mm256_srli_epi32(pixels, 24)),
+        unpack(_mm256_and_si256(_mm256_srli_epi32(pixels, 16), byte_mask)),
+        unpack(_mm256_and_si256(_mm256_srli_epi32(pixels, 8), byte_mask)),
+        unpack(_mm256_and_si256(pixels, byte_mask)),
+    ]
+}
 
-        for (pixel, delta) in std::iter::zip(dst_rem, cvg_rem) {
-            unsafe {
-                let deltas = _mm256_castps128_ps256(_mm_load_ss(delta));
-                let mask = self.accum(deltas);
-                _mm_store_ss(delta, _mm_set_ss(0.0));
-
-                let dst128 = _mm_loadu_si32(pixel as *const u32 as *const u8);
-                let dst = _mm256_castsi128_si256(dst128);
-                let out = self.blend(dst, mask);
-                let out128 = _mm256_castsi256_si128(out);
-                _mm_storeu_si32(pixel as *mut u32 as *mut u8, out128);
-            }
-        }
+#[inline(always)]
+unsafe fn pack(channels: [Avx2; 4]) -> __m256i {
+    let pack = |channel: Avx2| _mm256_cvtps_epi32(channel.0);
+    let [a, r, g, b] = map4(channels, pack);
+
+    let out = _mm256_slli_epi32(a, 24);
+    let out = _mm256_or_si256(out, _mm256_slli_epi32(r, 16));
+    let out = _mm256_or_si256(out, _mm256_slli_epi32(g, 8));
+    _mm256_or_si256(out, b)
+}
+
+impl Add for Avx2 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        unsafe { Avx2(_mm256_add_ps(self.0, rhs.0)) }
+    }
+}
+
+impl Sub for Avx2 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        unsafe { Avx2(_mm256_sub_ps(self.0, rhs.0)) }
+    }
+}
+
+impl Mul for Avx2 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, rhs: Self) -> Self {
+        unsafe { Avx2(_mm256_mul_ps(self.0, rhs.0)) }
+    }
+}
+
+impl Div for Avx2 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn div(self, rhs: Self) -> Self {
+        unsafe { Avx2(_mm256_div_ps(self.0, rhs.0)) }
     }
 }
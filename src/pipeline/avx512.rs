@@ -0,0 +1,310 @@
+#[cfg(target_arch = "x86")]
+use std::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+use std::ops::{Add, Div, Mul, Sub};
+
+use super::{map4, Simd};
+
+#[derive(Copy, Clone)]
+pub struct Avx512(__m512);
+
+impl Simd for Avx512 {
+    const LANES: usize = 16;
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn vectorize<R>(f: impl FnOnce() -> R) -> R {
+        f()
+    }
+
+    #[inline(always)]
+    fn splat(value: f32) -> Self {
+        unsafe { Avx512(_mm512_set1_ps(value)) }
+    }
+
+    #[inline(always)]
+    fn ramp() -> Self {
+        unsafe {
+            Avx512(_mm512_setr_ps(
+                0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 11.0, 12.0, 13.0, 14.0,
+                15.0,
+            ))
+        }
+    }
+
+    #[inline(always)]
+    fn load(src: &[f32]) -> Self {
+        assert!(src.len() >= Self::LANES);
+        unsafe { Avx512(_mm512_loadu_ps(src.as_ptr())) }
+    }
+
+    #[inline(always)]
+    fn store(self, dst: &mut [f32]) {
+        assert!(dst.len() >= Self::LANES);
+        unsafe { _mm512_storeu_ps(dst.as_mut_ptr(), self.0) }
+    }
+
+    #[inline(always)]
+    fn min(self, other: Self) -> Self {
+        unsafe { Avx512(_mm512_min_ps(self.0, other.0)) }
+    }
+
+    #[inline(always)]
+    fn max(self, other: Self) -> Self {
+        unsafe { Avx512(_mm512_max_ps(self.0, other.0)) }
+    }
+
+    #[inline(always)]
+    fn abs(self) -> Self {
+        unsafe { Avx512(_mm512_abs_ps(self.0)) }
+    }
+
+    #[inline(always)]
+    fn floor(self) -> Self {
+        unsafe {
+            Avx512(_mm512_roundscale_ps(
+                self.0,
+                _MM_FROUND_TO_NEG_INF | _MM_FROUND_NO_EXC,
+            ))
+        }
+    }
+
+    #[inline(always)]
+    fn round(self) -> Self {
+        unsafe {
+            Avx512(_mm512_roundscale_ps(
+                self.0,
+                _MM_FROUND_TO_NEAREST_INT | _MM_FROUND_NO_EXC,
+            ))
+        }
+    }
+
+    #[inline(always)]
+    fn sqrt(self) -> Self {
+        unsafe { Avx512(_mm512_sqrt_ps(self.0)) }
+    }
+
+    #[inline(always)]
+    fn lt(self, other: Self) -> Self {
+        unsafe {
+            let mask = _mm512_cmp_ps_mask(self.0, other.0, _CMP_LT_OQ);
+            Avx512(_mm512_castsi512_ps(_mm512_maskz_set1_epi32(mask, -1)))
+        }
+    }
+
+    #[inline(always)]
+    fn select(mask: Self, if_true: Self, if_false: Self) -> Self {
+        unsafe {
+            // Like `blendv`, select on the sign bit of each lane of the mask.
+            let mask = _mm512_cmplt_epi32_mask(_mm512_castps_si512(mask.0), _mm512_setzero_si512());
+            Avx512(_mm512_mask_blend_ps(mask, if_false.0, if_true.0))
+        }
+    }
+
+    #[inline(always)]
+    fn prefix_sum(self) -> Self {
+        unsafe {
+            // Shift the lanes up by 1, 2, 4, and 8 places, shifting in zeros, and add.
+            let add = |a: __m512i, b: __m512i| {
+                _mm512_castps_si512(_mm512_add_ps(
+                    _mm512_castsi512_ps(a),
+                    _mm512_castsi512_ps(b),
+                ))
+            };
+            let zero = _mm512_setzero_si512();
+            let sum = _mm512_castps_si512(self.0);
+            let sum = add(sum, _mm512_alignr_epi32(sum, zero, 15));
+            let sum = add(sum, _mm512_alignr_epi32(sum, zero, 14));
+            let sum = add(sum, _mm512_alignr_epi32(sum, zero, 12));
+            let sum = add(sum, _mm512_alignr_epi32(sum, zero, 8));
+            Avx512(_mm512_castsi512_ps(sum))
+        }
+    }
+
+    #[inline(always)]
+    fn last(self) -> f32 {
+        unsafe {
+            let upper = _mm512_extractf32x4_ps(self.0, 3);
+            _mm_cvtss_f32(_mm_shuffle_ps(upper, upper, 0xFF))
+        }
+    }
+
+    #[inline(always)]
+    fn gather(table: &[f32], index: Self) -> Self {
+        unsafe {
+            let max = _mm512_set1_ps((table.len() - 1) as f32);
+            let clamped = _mm512_min_ps(_mm512_max_ps(index.0, _mm512_setzero_ps()), max);
+            let indices = _mm512_cvttps_epi32(clamped);
+            Avx512(_mm512_i32gather_ps(indices, table.as_ptr(), 4))
+        }
+    }
+
+    #[inline(always)]
+    fn gather_pixels(pixels: &[u32], stride: usize, x: Self, y: Self) -> [Self; 4] {
+        assert!(!pixels.is_empty() && pixels.len() <= i32::MAX as usize);
+        unsafe {
+            let x = _mm512_cvttps_epi32(x.0);
+            let y = _mm512_cvttps_epi32(y.0);
+            let index =
+                _mm512_add_epi32(_mm512_mullo_epi32(y, _mm512_set1_epi32(stride as i32)), x);
+
+            // Clamp the indices so that the gather can never read out of bounds.
+            let max = _mm512_set1_epi32(pixels.len() as i32 - 1);
+            let index = _mm512_max_epi32(_mm512_min_epi32(index, max), _mm512_setzero_si512());
+            unpack(_mm512_i32gather_epi32(
+                index,
+                pixels.as_ptr() as *const i32,
+                4,
+            ))
+        }
+    }
+
+    #[inline(always)]
+    fn unpack(src: &[u32]) -> [Self; 4] {
+        assert!(src.len() >= Self::LANES);
+        unsafe { unpack(_mm512_loadu_si512(src.as_ptr() as *const __m512i)) }
+    }
+
+    #[inline(always)]
+    fn pack(channels: [Self; 4], dst: &mut [u32]) {
+        assert!(dst.len() >= Self::LANES);
+        unsafe { _mm512_storeu_si512(dst.as_mut_ptr() as *mut __m512i, pack(channels)) }
+    }
+
+    #[inline(always)]
+    fn unpack_bytes(src: &[[u8; 4]]) -> [Self; 4] {
+        assert!(src.len() >= Self::LANES);
+        unsafe { unpack(_mm512_loadu_si512(src.as_ptr() as *const __m512i)) }
+    }
+
+    #[inline(always)]
+    fn pack_bytes(channels: [Self; 4], dst: &mut [[u8; 4]]) {
+        assert!(dst.len() >= Self::LANES);
+        unsafe { _mm512_storeu_si512(dst.as_mut_ptr() as *mut __m512i, pack(channels)) }
+    }
+
+    #[inline(always)]
+    fn unpack_bytes_partial(src: &[[u8; 4]]) -> [Self; 4] {
+        assert!(src.len() <= Self::LANES);
+        unsafe {
+            let mask = tail_mask(src.len());
+            unpack(_mm512_maskz_loadu_epi32(mask, src.as_ptr() as *const i32))
+        }
+    }
+
+    #[inline(always)]
+    fn pack_bytes_partial(channels: [Self; 4], dst: &mut [[u8; 4]]) {
+        assert!(dst.len() <= Self::LANES);
+        unsafe {
+            let mask = tail_mask(dst.len());
+            _mm512_mask_storeu_epi32(dst.as_mut_ptr() as *mut i32, mask, pack(channels));
+        }
+    }
+
+    #[inline(always)]
+    fn unpack_u8(src: &[u8]) -> Self {
+        assert!(src.len() >= Self::LANES);
+        unsafe {
+            let bytes = _mm_loadu_si128(src.as_ptr() as *const __m128i);
+            Avx512(_mm512_cvtepi32_ps(_mm512_cvtepu8_epi32(bytes)))
+        }
+    }
+
+    #[inline(always)]
+    fn load_partial(src: &[f32]) -> Self {
+        assert!(src.len() <= Self::LANES);
+        unsafe { Avx512(_mm512_maskz_loadu_ps(tail_mask(src.len()), src.as_ptr())) }
+    }
+
+    #[inline(always)]
+    fn store_partial(self, dst: &mut [f32]) {
+        assert!(dst.len() <= Self::LANES);
+        unsafe { _mm512_mask_storeu_ps(dst.as_mut_ptr(), tail_mask(dst.len()), self.0) }
+    }
+
+    #[inline(always)]
+    fn unpack_partial(src: &[u32]) -> [Self; 4] {
+        assert!(src.len() <= Self::LANES);
+        unsafe {
+            let mask = tail_mask(src.len());
+            unpack(_mm512_maskz_loadu_epi32(mask, src.as_ptr() as *const i32))
+        }
+    }
+
+    #[inline(always)]
+    fn pack_partial(channels: [Self; 4], dst: &mut [u32]) {
+        assert!(dst.len() <= Self::LANES);
+        unsafe {
+            let mask = tail_mask(dst.len());
+            _mm512_mask_storeu_epi32(dst.as_mut_ptr() as *mut i32, mask, pack(channels));
+        }
+    }
+}
+
+/// Returns a mask selecting the first `len` lanes, for use with masked loads and stores.
+#[inline(always)]
+fn tail_mask(len: usize) -> __mmask16 {
+    ((1u32 << len) - 1) as __mmask16
+}
+
+#[inline(always)]
+unsafe fn unpack(pixels: __m512i) -> [Avx512; 4] {
+    let byte_mask = _mm512_set1_epi32(0xFF);
+    let unpack = |channel: __m512i| Avx512(_mm512_cvtepi32_ps(channel));
+
+    [
+        unpack(_mm512_srli_epi32(pixels, 24)),
+        unpack(_mm512_and_si512(_mm512_srli_epi32(pixels, 16), byte_mask)),
+        unpack(_mm512_and_si512(_mm512_srli_epi32(pixels, 8), byte_mask)),
+        unpack(_mm512_and_si512(pixels, byte_mask)),
+    ]
+}
+
+#[inline(always)]
+unsafe fn pack(channels: [Avx512; 4]) -> __m512i {
+    let pack = |channel: Avx512| _mm512_cvtps_epi32(channel.0);
+    let [a, r, g, b] = map4(channels, pack);
+
+    let out = _mm512_slli_epi32(a, 24);
+    let out = _mm512_or_si512(out, _mm512_slli_epi32(r, 16));
+    let out = _mm512_or_si512(out, _mm512_slli_epi32(g, 8));
+    _mm512_or_si512(out, b)
+}
+
+impl Add for Avx512 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        unsafe { Avx512(_mm512_add_ps(self.0, rhs.0)) }
+    }
+}
+
+impl Sub for Avx512 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        unsafe { Avx512(_mm512_sub_ps(self.0, rhs.0)) }
+    }
+}
+
+impl Mul for Avx512 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, rhs: Self) -> Self {
+        unsafe { Avx512(_mm512_mul_ps(self.0, rhs.0)) }
+    }
+}
+
+impl Div for Avx512 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn div(self, rhs: Self) -> Self {
+        unsafe { Avx512(_mm512_div_ps(self.0, rhs.0)) }
+    }
+}
@@ -0,0 +1,202 @@
+use super::{map3, map4, Simd};
+use crate::blend::{BlendMode, CompositeOp};
+
+/// Combines premultiplied source and destination colors, given as a, r, g, and b components. When
+/// `clamped` is set, results are limited to at most 1.
+#[inline(always)]
+pub fn composite<S: Simd>(op: CompositeOp, src: [S; 4], dst: [S; 4], clamped: bool) -> [S; 4] {
+    let zero = S::splat(0.0);
+    let one = S::splat(1.0);
+    let src_a = src[0];
+    let dst_a = dst[0];
+
+    // The result is src * src_factor + dst * dst_factor for all operators except Plus.
+    let (src_factor, dst_factor) = match op {
+        CompositeOp::Clear => (zero, zero),
+        CompositeOp::Src => (one, zero),
+        CompositeOp::SrcOver => (one, one - src_a),
+        CompositeOp::SrcIn => (dst_a, zero),
+        CompositeOp::SrcOut => (one - dst_a, zero),
+        CompositeOp::SrcAtop => (dst_a, one - src_a),
+        CompositeOp::DstOver => (one - dst_a, one),
+        CompositeOp::DstIn => (zero, src_a),
+        CompositeOp::DstOut => (zero, one - src_a),
+        CompositeOp::DstAtop => (one - dst_a, src_a),
+        CompositeOp::Xor => (one - dst_a, one - src_a),
+        CompositeOp::Plus => {
+            let sum = map4([0, 1, 2, 3], |c| src[c] + dst[c]);
+            return if clamped {
+                map4(sum, |c| c.min(one))
+            } else {
+                sum
+            };
+        }
+    };
+
+    map4([0, 1, 2, 3], |c| src[c] * src_factor + dst[c] * dst_factor)
+}
+
+/// Mixes the premultiplied source color with the destination color according to `mode`, returning
+/// the new source color. Alpha is left unchanged.
+#[inline(always)]
+pub fn blend<S: Simd>(mode: BlendMode, src: [S; 4], dst: [S; 4]) -> [S; 4] {
+    let one = S::splat(1.0);
+    let src_a = src[0];
+    let dst_a = dst[0];
+
+    // The blend functions operate on unpremultiplied colors.
+    let cs = unpremultiply(src);
+    let cb = unpremultiply(dst);
+
+    let mixed = match mode {
+        BlendMode::Normal => return src,
+        BlendMode::Hue => set_lum(set_sat(cs, sat(cb)), lum(cb)),
+        BlendMode::Saturation => set_lum(set_sat(cb, sat(cs)), lum(cb)),
+        BlendMode::Color => set_lum(cs, lum(cb)),
+        BlendMode::Luminosity => set_lum(cb, lum(cs)),
+        _ => map3(
+            [0, 1, 2],
+            #[inline(always)]
+            |c| separable(mode, cb[c], cs[c]),
+        ),
+    };
+
+    // Where the destination is partially transparent, the blended color is mixed with the
+    // unblended source color.
+    let inv_dst_a = one - dst_a;
+    let both_a = src_a * dst_a;
+    let [r, g, b] = map3([0, 1, 2], |c| inv_dst_a * src[c + 1] + both_a * mixed[c]);
+    [src_a, r, g, b]
+}
+
+/// Converts the color channels of a premultiplied color to unpremultiplied r, g, and b.
+#[inline(always)]
+fn unpremultiply<S: Simd>(color: [S; 4]) -> [S; 3] {
+    let zero = S::splat(0.0);
+    let a = color[0];
+    let present = zero.lt(a);
+    map3(
+        [1, 2, 3],
+        #[inline(always)]
+        |c| S::select(present, color[c] / a, zero),
+    )
+}
+
+/// Applies a separable blend function to a single color channel.
+#[inline(always)]
+fn separable<S: Simd>(mode: BlendMode, cb: S, cs: S) -> S {
+    match mode {
+        BlendMode::Multiply => multiply(cb, cs),
+        BlendMode::Screen => screen(cb, cs),
+        BlendMode::Overlay => hard_light(cs, cb),
+        BlendMode::Darken => cb.min(cs),
+        BlendMode::Lighten => cb.max(cs),
+        BlendMode::ColorDodge => color_dodge(cb, cs),
+        BlendMode::ColorBurn => color_burn(cb, cs),
+        BlendMode::HardLight => hard_light(cb, cs),
+        BlendMode::SoftLight => soft_light(cb, cs),
+        BlendMode::Difference => (cb - cs).abs(),
+        BlendMode::Exclusion => cb + cs - S::splat(2.0) * cb * cs,
+        _ => unreachable!(),
+    }
+}
+
+#[inline(always)]
+fn multiply<S: Simd>(cb: S, cs: S) -> S {
+    cb * cs
+}
+
+#[inline(always)]
+fn screen<S: Simd>(cb: S, cs: S) -> S {
+    cb + cs - cb * cs
+}
+
+#[inline(always)]
+fn hard_light<S: Simd>(cb: S, cs: S) -> S {
+    let one = S::splat(1.0);
+    let cs2 = S::splat(2.0) * cs;
+    S::select(
+        S::splat(0.5).lt(cs),
+        screen(cb, cs2 - one),
+        multiply(cb, cs2),
+    )
+}
+
+#[inline(always)]
+fn color_dodge<S: Simd>(cb: S, cs: S) -> S {
+    let zero = S::splat(0.0);
+    let one = S::splat(1.0);
+    let dodged = S::select(cs.lt(one), (cb / (one - cs)).min(one), one);
+    S::select(zero.lt(cb), dodged, zero)
+}
+
+#[inline(always)]
+fn color_burn<S: Simd>(cb: S, cs: S) -> S {
+    let zero = S::splat(0.0);
+    let one = S::splat(1.0);
+    let burned = S::select(zero.lt(cs), one - ((one - cb) / cs).min(one), zero);
+    S::select(cb.lt(one), burned, one)
+}
+
+#[inline(always)]
+fn soft_light<S: Simd>(cb: S, cs: S) -> S {
+    let one = S::splat(1.0);
+    let cs2 = S::splat(2.0) * cs;
+
+    let poly = ((S::splat(16.0) * cb - S::splat(12.0)) * cb + S::splat(4.0)) * cb;
+    let d = S::select(S::splat(0.25).lt(cb), cb.sqrt(), poly);
+
+    S::select(
+        S::splat(0.5).lt(cs),
+        cb + (cs2 - one) * (d - cb),
+        cb - (one - cs2) * cb * (one - cb),
+    )
+}
+
+#[inline(always)]
+fn lum<S: Simd>(c: [S; 3]) -> S {
+    S::splat(0.3) * c[0] + S::splat(0.59) * c[1] + S::splat(0.11) * c[2]
+}
+
+#[inline(always)]
+fn sat<S: Simd>(c: [S; 3]) -> S {
+    c[0].max(c[1]).max(c[2]) - c[0].min(c[1]).min(c[2])
+}
+
+#[inline(always)]
+fn set_lum<S: Simd>(c: [S; 3], l: S) -> [S; 3] {
+    let zero = S::splat(0.0);
+    let one = S::splat(1.0);
+
+    let d = l - lum(c);
+    let c = map3(c, |c| c + d);
+
+    // Bring the channels back into range while preserving luminosity. Rounding can put the
+    // luminosity itself out of range when all channels are equal, in which case they are left
+    // unchanged rather than dividing by zero.
+    let l = lum(c);
+    let min = c[0].min(c[1]).min(c[2]);
+    let max = c[0].max(c[1]).max(c[2]);
+    let below = min.lt(zero.min(l));
+    let above = one.max(l).lt(max);
+    map3(
+        c,
+        #[inline(always)]
+        |c| {
+            let c = S::select(below, l + (c - l) * l / (l - min), c);
+            S::select(above, l + (c - l) * (one - l) / (max - l), c)
+        },
+    )
+}
+
+#[inline(always)]
+fn set_sat<S: Simd>(c: [S; 3], s: S) -> [S; 3] {
+    let zero = S::splat(0.0);
+    let min = c[0].min(c[1]).min(c[2]);
+    let max = c[0].max(c[1]).max(c[2]);
+    let range = max - min;
+
+    // Stretches the channels so that the smallest becomes 0 and the largest becomes `s`.
+    let nonempty = zero.lt(range);
+    map3(c, |c| S::select(nonempty, (c - min) * s / range, zero))
+}
@@ -0,0 +1,46 @@
+use super::{Simd, MAX_LANES};
+
+/// An 8x8 ordered dithering matrix, holding thresholds from 0 to 63.
+const BAYER: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// Offsets the red, green, and blue channels of the pixels starting at column `x` of row `y` by up
+/// to half of the corresponding `step` in either direction, so that rounding to multiples of the
+/// step spreads error out over neighboring pixels instead of producing bands. Values which are
+/// already multiples of the step are left unchanged by rounding.
+// Kept out of line since dithering is optional.
+#[inline(never)]
+pub fn dither<S: Simd>(x: usize, y: usize, step: [f32; 3], channels: [S; 4]) -> [S; 4] {
+    // SAFETY: Pipelines only run on backends which the CPU supports.
+    unsafe {
+        S::vectorize(
+            #[inline(always)]
+            || {
+                let row = &BAYER[y % 8];
+                let mut offsets = [0.0; MAX_LANES];
+                for (i, offset) in offsets.iter_mut().enumerate().take(S::LANES) {
+                    *offset = (row[(x + i) % 8] as f32 + 0.5) * (1.0 / 64.0) - 0.5;
+                }
+                let offset = S::load(&offsets);
+
+                let [a, r, g, b] = channels;
+                let zero = S::splat(0.0);
+                let dither = |c: S, step: f32| (c + offset * S::splat(step)).max(zero).min(a);
+                [
+                    a,
+                    dither(r, step[0]),
+                    dither(g, step[1]),
+                    dither(b, step[2]),
+                ]
+            },
+        )
+    }
+}
@@ -0,0 +1,445 @@
+use std::marker::PhantomData;
+
+use super::{copy, map4, Simd, MAX_LANES};
+use crate::Color;
+
+/// A pixel format which a pipeline can load from and store to. Channels are converted to and from
+/// premultiplied a, r, g, and b values between 0 and 1.
+pub trait Format {
+    type Pixel: Copy + Default;
+
+    /// Whether stored values are limited to between 0 and 1.
+    const CLAMPED: bool = true;
+    /// The spacing of the red, green, and blue values which the format can represent, or 0 if
+    /// colors aren't dithered.
+    const DITHER_STEP: [f32; 3] = [1.0 / 255.0; 3];
+
+    fn unpack<S: Simd>(src: &[Self::Pixel]) -> [S; 4];
+    /// Values must be between 0 and 1.
+    fn pack<S: Simd>(channels: [S; 4], dst: &mut [Self::Pixel]);
+
+    /// Converts an opaque color.
+    fn opaque(color: Color) -> Self::Pixel;
+
+    /// The value which `unpack_scaled` and `pack_scaled` use for a channel value of 1.
+    const SCALE: f32 = 1.0;
+
+    /// Like `unpack`, but with channels multiplied by `SCALE`, for loops which only combine
+    /// channels linearly and can skip converting them to and from values between 0 and 1.
+    #[inline(always)]
+    fn unpack_scaled<S: Simd>(src: &[Self::Pixel]) -> [S; 4] {
+        Self::unpack(src)
+    }
+
+    /// The inverse of `unpack_scaled`.
+    #[inline(always)]
+    fn pack_scaled<S: Simd>(channels: [S; 4], dst: &mut [Self::Pixel]) {
+        Self::pack(channels, dst)
+    }
+
+    /// Like `unpack_scaled`, but for fewer than `LANES` pixels.
+    #[inline(always)]
+    fn unpack_scaled_partial<S: Simd>(src: &[Self::Pixel]) -> [S; 4] {
+        Self::unpack_partial(src)
+    }
+
+    /// Like `pack_scaled`, but for fewer than `LANES` pixels.
+    #[inline(always)]
+    fn pack_scaled_partial<S: Simd>(channels: [S; 4], dst: &mut [Self::Pixel]) {
+        Self::pack_partial(channels, dst)
+    }
+
+    /// Like `unpack`, but for fewer than `LANES` pixels.
+    #[inline(always)]
+    fn unpack_partial<S: Simd>(src: &[Self::Pixel]) -> [S; 4] {
+        let mut buf = [Self::Pixel::default(); MAX_LANES];
+        copy(src, &mut buf);
+        Self::unpack(&buf)
+    }
+
+    /// Like `pack`, but for fewer than `LANES` pixels.
+    #[inline(always)]
+    fn pack_partial<S: Simd>(channels: [S; 4], dst: &mut [Self::Pixel]) {
+        let mut buf = [Self::Pixel::default(); MAX_LANES];
+        Self::pack(channels, &mut buf);
+        copy(&buf, dst);
+    }
+}
+
+/// Native-endian `0xAARRGGBB` values, with premultiplied alpha unless `STRAIGHT` is set.
+pub struct Argb32<const STRAIGHT: bool>;
+
+impl<const STRAIGHT: bool> Format for Argb32<STRAIGHT> {
+    type Pixel = u32;
+
+    #[inline(always)]
+    fn unpack<S: Simd>(src: &[u32]) -> [S; 4] {
+        premultiply::<S, STRAIGHT>(from_bytes(S::unpack(src)))
+    }
+
+    #[inline(always)]
+    fn pack<S: Simd>(channels: [S; 4], dst: &mut [u32]) {
+        S::pack(to_bytes(unpremultiply::<S, STRAIGHT>(channels)), dst)
+    }
+
+    #[inline(always)]
+    fn opaque(color: Color) -> u32 {
+        color.into()
+    }
+
+    #[inline(always)]
+    fn unpack_partial<S: Simd>(src: &[u32]) -> [S; 4] {
+        premultiply::<S, STRAIGHT>(from_bytes(S::unpack_partial(src)))
+    }
+
+    #[inline(always)]
+    fn pack_partial<S: Simd>(channels: [S; 4], dst: &mut [u32]) {
+        S::pack_partial(to_bytes(unpremultiply::<S, STRAIGHT>(channels)), dst)
+    }
+
+    // Premultiplied channels are combined linearly as they are stored, between 0 and 255.
+    const SCALE: f32 = if STRAIGHT { 1.0 } else { 255.0 };
+
+    #[inline(always)]
+    fn unpack_scaled<S: Simd>(src: &[u32]) -> [S; 4] {
+        if STRAIGHT {
+            return Self::unpack(src);
+        }
+
+        S::unpack(src)
+    }
+
+    #[inline(always)]
+    fn pack_scaled<S: Simd>(channels: [S; 4], dst: &mut [u32]) {
+        if STRAIGHT {
+            return Self::pack(channels, dst);
+        }
+
+        S::pack(channels, dst)
+    }
+
+    #[inline(always)]
+    fn unpack_scaled_partial<S: Simd>(src: &[u32]) -> [S; 4] {
+        if STRAIGHT {
+            return Self::unpack_partial(src);
+        }
+
+        S::unpack_partial(src)
+    }
+
+    #[inline(always)]
+    fn pack_scaled_partial<S: Simd>(channels: [S; 4], dst: &mut [u32]) {
+        if STRAIGHT {
+            return Self::pack_partial(channels, dst);
+        }
+
+        S::pack_partial(channels, dst)
+    }
+}
+
+/// Four bytes per pixel, with premultiplied alpha unless `STRAIGHT` is set. Read as little-endian
+/// `u32` values, pixels are `0xAARRGGBB` like `Argb32`, or `0xAABBGGRR` if `SWAP` is set, so they
+/// are loaded and stored like `Argb32` with red and blue swapped.
+pub struct Bytes<const STRAIGHT: bool, const SWAP: bool>;
+
+/// Bytes in R, G, B, A order, with premultiplied alpha unless `STRAIGHT` is set.
+pub type Rgba8<const STRAIGHT: bool> = Bytes<STRAIGHT, true>;
+
+/// Bytes in B, G, R, A order, with premultiplied alpha unless `STRAIGHT` is set.
+pub type Bgra8<const STRAIGHT: bool> = Bytes<STRAIGHT, false>;
+
+impl<const STRAIGHT: bool, const SWAP: bool> Format for Bytes<STRAIGHT, SWAP> {
+    type Pixel = [u8; 4];
+
+    #[inline(always)]
+    fn unpack<S: Simd>(src: &[[u8; 4]]) -> [S; 4] {
+        premultiply::<S, STRAIGHT>(from_bytes(swap::<S, SWAP>(S::unpack_bytes(src))))
+    }
+
+    #[inline(always)]
+    fn pack<S: Simd>(channels: [S; 4], dst: &mut [[u8; 4]]) {
+        S::pack_bytes(
+            swap::<S, SWAP>(to_bytes(unpremultiply::<S, STRAIGHT>(channels))),
+            dst,
+        )
+    }
+
+    #[inline(always)]
+    fn opaque(color: Color) -> [u8; 4] {
+        if SWAP {
+            [color.r(), color.g(), color.b(), 255]
+        } else {
+            [color.b(), color.g(), color.r(), 255]
+        }
+    }
+
+    #[inline(always)]
+    fn unpack_partial<S: Simd>(src: &[[u8; 4]]) -> [S; 4] {
+        premultiply::<S, STRAIGHT>(from_bytes(swap::<S, SWAP>(S::unpack_bytes_partial(src))))
+    }
+
+    #[inline(always)]
+    fn pack_partial<S: Simd>(channels: [S; 4], dst: &mut [[u8; 4]]) {
+        let channels = swap::<S, SWAP>(to_bytes(unpremultiply::<S, STRAIGHT>(channels)));
+        S::pack_bytes_partial(channels, dst)
+    }
+
+    // Like `Argb32`, premultiplied channels are combined linearly between 0 and 255.
+    const SCALE: f32 = if STRAIGHT { 1.0 } else { 255.0 };
+
+    #[inline(always)]
+    fn unpack_scaled<S: Simd>(src: &[[u8; 4]]) -> [S; 4] {
+        if STRAIGHT {
+            return Self::unpack(src);
+        }
+
+        swap::<S, SWAP>(S::unpack_bytes(src))
+    }
+
+    #[inline(always)]
+    fn pack_scaled<S: Simd>(channels: [S; 4], dst: &mut [[u8; 4]]) {
+        if STRAIGHT {
+            return Self::pack(channels, dst);
+        }
+
+        S::pack_bytes(swap::<S, SWAP>(channels), dst)
+    }
+
+    #[inline(always)]
+    fn unpack_scaled_partial<S: Simd>(src: &[[u8; 4]]) -> [S; 4] {
+        if STRAIGHT {
+            return Self::unpack_partial(src);
+        }
+
+        swap::<S, SWAP>(S::unpack_bytes_partial(src))
+    }
+
+    #[inline(always)]
+    fn pack_scaled_partial<S: Simd>(channels: [S; 4], dst: &mut [[u8; 4]]) {
+        if STRAIGHT {
+            return Self::pack_partial(channels, dst);
+        }
+
+        S::pack_bytes_partial(swap::<S, SWAP>(channels), dst)
+    }
+}
+
+/// Alpha only, with color discarded.
+pub struct A8;
+
+impl Format for A8 {
+    type Pixel = u8;
+
+    const DITHER_STEP: [f32; 3] = [0.0; 3];
+
+    #[inline(always)]
+    fn unpack<S: Simd>(src: &[u8]) -> [S; 4] {
+        let zero = S::splat(0.0);
+        [S::unpack_u8(src) * S::splat(1.0 / 255.0), zero, zero, zero]
+    }
+
+    #[inline(always)]
+    fn unpack_partial<S: Simd>(src: &[u8]) -> [S; 4] {
+        let zero = S::splat(0.0);
+        [
+            S::unpack_u8_partial(src) * S::splat(1.0 / 255.0),
+            zero,
+            zero,
+            zero,
+        ]
+    }
+
+    #[inline(always)]
+    fn pack<S: Simd>(channels: [S; 4], dst: &mut [u8]) {
+        let mut buf = [0; MAX_LANES];
+        S::pack(to_bytes(channels), &mut buf);
+        for (alpha, &pixel) in dst.iter_mut().zip(&buf).take(S::LANES) {
+            *alpha = (pixel >> 24) as u8;
+        }
+    }
+
+    #[inline(always)]
+    fn opaque(_color: Color) -> u8 {
+        255
+    }
+}
+
+/// Native-endian 16-bit values with 5 bits of red, 6 bits of green, and 5 bits of blue, from most to
+/// least significant. Pixels are always opaque, so alpha is discarded.
+pub struct Rgb565;
+
+impl Format for Rgb565 {
+    type Pixel = u16;
+
+    const DITHER_STEP: [f32; 3] = [1.0 / 31.0, 1.0 / 63.0, 1.0 / 31.0];
+
+    #[inline(always)]
+    fn unpack<S: Simd>(src: &[u16]) -> [S; 4] {
+        let mut buf = [0; MAX_LANES];
+        for (pixel, &value) in buf.iter_mut().zip(src).take(S::LANES) {
+            *pixel = rgb565_to_argb(value);
+        }
+        from_bytes(S::unpack(&buf))
+    }
+
+    #[inline(always)]
+    fn pack<S: Simd>(channels: [S; 4], dst: &mut [u16]) {
+        let mut buf = [0; MAX_LANES];
+        S::pack(to_bytes(channels), &mut buf);
+        for (value, &pixel) in dst.iter_mut().zip(&buf).take(S::LANES) {
+            *value = argb_to_rgb565(pixel);
+        }
+    }
+
+    #[inline(always)]
+    fn opaque(color: Color) -> u16 {
+        argb_to_rgb565(color.into())
+    }
+}
+
+/// Four `f32` values per pixel in R, G, B, A order, with premultiplied alpha. Values are stored as
+/// they are, without rounding or clamping.
+pub struct RgbaF32;
+
+impl Format for RgbaF32 {
+    type Pixel = [f32; 4];
+
+    const CLAMPED: bool = false;
+    const DITHER_STEP: [f32; 3] = [0.0; 3];
+
+    #[inline(always)]
+    fn unpack<S: Simd>(src: &[[f32; 4]]) -> [S; 4] {
+        let mut channels = [[0.0; MAX_LANES]; 4];
+        for (i, &[r, g, b, a]) in src.iter().enumerate().take(S::LANES) {
+            channels[0][i] = a;
+            channels[1][i] = r;
+            channels[2][i] = g;
+            channels[3][i] = b;
+        }
+        map4(channels, |channel| S::load(&channel))
+    }
+
+    #[inline(always)]
+    fn pack<S: Simd>(channels: [S; 4], dst: &mut [[f32; 4]]) {
+        let mut values = [[0.0; MAX_LANES]; 4];
+        for (channel, values) in channels.iter().zip(&mut values) {
+            channel.store(values);
+        }
+        let [a, r, g, b] = values;
+        for (i, pixel) in dst.iter_mut().enumerate().take(S::LANES) {
+            *pixel = [r[i], g[i], b[i], a[i]];
+        }
+    }
+
+    #[inline(always)]
+    fn opaque(color: Color) -> [f32; 4] {
+        let scale = 1.0 / 255.0;
+        [
+            color.r() as f32 * scale,
+            color.g() as f32 * scale,
+            color.b() as f32 * scale,
+            1.0,
+        ]
+    }
+}
+
+/// Expands each channel to 8 bits by replicating its high bits.
+#[inline(always)]
+pub fn rgb565_to_argb(value: u16) -> u32 {
+    let value = value as u32;
+    let r = value >> 11 & 0x1F;
+    let g = value >> 5 & 0x3F;
+    let b = value & 0x1F;
+    0xFF00_0000 | (r << 3 | r >> 2) << 16 | (g << 2 | g >> 4) << 8 | (b << 3 | b >> 2)
+}
+
+/// Rounds each channel of a pixel to the nearest 5- or 6-bit value.
+#[inline(always)]
+pub fn argb_to_rgb565(pixel: u32) -> u16 {
+    let r = ((pixel >> 16 & 0xFF) * 31 + 127) / 255;
+    let g = ((pixel >> 8 & 0xFF) * 63 + 127) / 255;
+    let b = ((pixel & 0xFF) * 31 + 127) / 255;
+    (r << 11 | g << 5 | b) as u16
+}
+
+/// Converts channels unpacked from 8-bit values to values between 0 and 1.
+#[inline(always)]
+pub fn from_bytes<S: Simd>(channels: [S; 4]) -> [S; 4] {
+    map4(channels, |c| c * S::splat(1.0 / 255.0))
+}
+
+/// Converts channels between 0 and 1 to values for packing into 8-bit values.
+#[inline(always)]
+fn to_bytes<S: Simd>(channels: [S; 4]) -> [S; 4] {
+    map4(channels, |c| c * S::splat(255.0))
+}
+
+/// A view of another format which unpacks and packs channels scaled by its `SCALE`.
+pub struct Scaled<F>(PhantomData<F>);
+
+impl<F: Format> Format for Scaled<F> {
+    type Pixel = F::Pixel;
+
+    const CLAMPED: bool = F::CLAMPED;
+    const DITHER_STEP: [f32; 3] = F::DITHER_STEP;
+
+    #[inline(always)]
+    fn unpack<S: Simd>(src: &[F::Pixel]) -> [S; 4] {
+        F::unpack_scaled(src)
+    }
+
+    #[inline(always)]
+    fn pack<S: Simd>(channels: [S; 4], dst: &mut [F::Pixel]) {
+        F::pack_scaled(channels, dst)
+    }
+
+    #[inline(always)]
+    fn opaque(color: Color) -> F::Pixel {
+        F::opaque(color)
+    }
+
+    #[inline(always)]
+    fn unpack_partial<S: Simd>(src: &[F::Pixel]) -> [S; 4] {
+        F::unpack_scaled_partial(src)
+    }
+
+    #[inline(always)]
+    fn pack_partial<S: Simd>(channels: [S; 4], dst: &mut [F::Pixel]) {
+        F::pack_scaled_partial(channels, dst)
+    }
+}
+
+/// Swaps the red and blue channels if `SWAP` is set.
+#[inline(always)]
+fn swap<S: Simd, const SWAP: bool>(channels: [S; 4]) -> [S; 4] {
+    if !SWAP {
+        return channels;
+    }
+
+    let [a, r, g, b] = channels;
+    [a, b, g, r]
+}
+
+#[inline(always)]
+fn premultiply<S: Simd, const STRAIGHT: bool>(channels: [S; 4]) -> [S; 4] {
+    if !STRAIGHT {
+        return channels;
+    }
+
+    let [a, r, g, b] = channels;
+    [a, r * a, g * a, b * a]
+}
+
+#[inline(always)]
+fn unpremultiply<S: Simd, const STRAIGHT: bool>(channels: [S; 4]) -> [S; 4] {
+    if !STRAIGHT {
+        return channels;
+    }
+
+    let [a, r, g, b] = channels;
+    let zero = S::splat(0.0);
+    let one = S::splat(1.0);
+    let present = zero.lt(a);
+    let unpremultiply = |c: S| S::select(present, (c / a).min(one), zero);
+    [a, unpremultiply(r), unpremultiply(g), unpremultiply(b)]
+}
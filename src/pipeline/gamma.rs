@@ -0,0 +1,122 @@
+use super::{map3, Simd, MAX_LANES};
+
+/// The number of entries in the table for encoding linear values as sRGB.
+const ENCODE_LEN: usize = 1024;
+
+/// Lookup tables for converting between sRGB and linear light.
+pub struct Srgb {
+    decode: Box<[f32]>,
+    encode: Box<[f32]>,
+}
+
+impl Srgb {
+    pub fn new() -> Srgb {
+        let decode = (0..256).map(|i| srgb_to_linear(i as f32 / 255.0)).collect();
+        let encode = (0..ENCODE_LEN)
+            .map(|i| linear_to_srgb(i as f32 / (ENCODE_LEN - 1) as f32))
+            .collect();
+
+        Srgb { decode, encode }
+    }
+
+    /// Converts premultiplied sRGB channels to premultiplied linear channels. Unless `clamped` is
+    /// set, colors may be brighter than 1, beyond the end of the table, and are then converted
+    /// exactly.
+    #[inline(always)]
+    pub fn to_linear<S: Simd>(&self, channels: [S; 4], clamped: bool) -> [S; 4] {
+        apply(&self.decode, srgb_to_linear, channels, clamped)
+    }
+
+    /// The inverse of `to_linear`.
+    #[inline(always)]
+    pub fn to_srgb<S: Simd>(&self, channels: [S; 4], clamped: bool) -> [S; 4] {
+        apply(&self.encode, linear_to_srgb, channels, clamped)
+    }
+}
+
+impl Default for Srgb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Maps the unpremultiplied value of each color channel, scaled to the length of `table`, through
+/// `table`, which samples `convert` between 0 and 1.
+#[inline(always)]
+fn apply<S: Simd>(
+    table: &[f32],
+    convert: fn(f32) -> f32,
+    channels: [S; 4],
+    clamped: bool,
+) -> [S; 4] {
+    let [a, r, g, b] = channels;
+    let zero = S::splat(0.0);
+    let scale = S::select(zero.lt(a), S::splat((table.len() - 1) as f32) / a, zero);
+    let [r, g, b] = map3(
+        [r, g, b],
+        #[inline(always)]
+        |c| {
+            let index = c * scale;
+            let value = lookup(table, index);
+            if clamped {
+                value
+            } else {
+                convert_beyond(table, convert, index, value)
+            }
+        },
+    );
+    [a, r * a, g * a, b * a]
+}
+
+/// Replaces the lanes of `value` whose `index` lies past the end of `table` with the exact result
+/// of `convert`, since the table lookup clamps them to 1.
+#[inline(always)]
+fn convert_beyond<S: Simd>(table: &[f32], convert: fn(f32) -> f32, index: S, value: S) -> S {
+    let last = (table.len() - 1) as f32;
+    let mut indices = [0.0; MAX_LANES];
+    index.store(&mut indices);
+    if indices[..S::LANES].iter().all(|&index| index <= last) {
+        return value;
+    }
+
+    let mut values = [0.0; MAX_LANES];
+    value.store(&mut values);
+    for (value, &index) in values.iter_mut().zip(&indices).take(S::LANES) {
+        if index > last {
+            *value = convert(index / last);
+        }
+    }
+    S::load(&values)
+}
+
+/// Looks up `index` in `table`, interpolating linearly between entries. Interpolating keeps 8-bit
+/// values unchanged when they are decoded and encoded again, even once divided by alpha.
+#[inline(always)]
+fn lookup<S: Simd>(table: &[f32], index: S) -> S {
+    let lower = index.floor();
+    let t = index - lower;
+    let low = S::gather(table, lower);
+    let high = S::gather(table, lower + S::splat(1.0));
+    low + t * (high - low)
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Builds a table which maps coverage `c` to `c^(1 / gamma)`, for use with `Options::coverage`.
+pub fn coverage_table(gamma: f32) -> Box<[f32]> {
+    (0..256).map(|i| (i as f32 / 255.0).powf(1.0 / gamma)).collect()
+}
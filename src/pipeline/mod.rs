@@ -1,23 +1,36 @@
-use crate::Color;
+use std::ops::{Add, Div, Mul, Sub};
+
+use crate::{Blend, BlendMode, CompositeOp, FillRule};
+
+mod blend;
+mod dither;
+mod format;
+mod gamma;
+mod shader;
+use format::Scaled;
+pub use format::{Argb32, Bgra8, Format, Rgb565, Rgba8, RgbaF32, A8};
+pub use gamma::{coverage_table, Srgb};
+pub use shader::Shader;
 
 mod scalar;
 #[allow(unused)]
 pub use scalar::Scalar;
 
 #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-#[cfg(target_feature = "sse2")]
 mod sse2;
 #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-#[cfg(target_feature = "sse2")]
 pub use sse2::Sse2;
 
 #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-#[cfg(target_feature = "avx2")]
 mod avx2;
 #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-#[cfg(target_feature = "avx2")]
 pub use avx2::Avx2;
 
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod avx512;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub use avx512::Avx512;
+
 #[cfg(target_arch = "aarch64")]
 #[cfg(target_feature = "neon")]
 mod neon;
@@ -25,9 +38,624 @@ mod neon;
 #[cfg(target_feature = "neon")]
 pub use neon::Neon;
 
-pub trait Pipeline {
-    fn build(color: Color) -> Self;
-    fn reset(&mut self);
-    fn fill(&mut self, dst: &mut [u32]);
-    fn fill_edge(&mut self, dst: &mut [u32], cvg: &mut [f32]);
+#[cfg(target_arch = "wasm32")]
+#[cfg(target_feature = "simd128")]
+mod wasm128;
+#[cfg(target_arch = "wasm32")]
+#[cfg(target_feature = "simd128")]
+pub use wasm128::Wasm128;
+
+/// An implementation of the pipelines, using a particular instruction set.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Backend {
+    Scalar,
+    Sse2,
+    Avx2,
+    Avx512,
+    Neon,
+    Wasm128,
+}
+
+impl Backend {
+    /// Returns the fastest backend which the CPU supports.
+    pub fn detect() -> Backend {
+        [
+            Backend::Avx512,
+            Backend::Avx2,
+            Backend::Sse2,
+            Backend::Neon,
+            Backend::Wasm128,
+        ]
+        .into_iter()
+        .find(|backend| backend.is_supported())
+        .unwrap_or(Backend::Scalar)
+    }
+
+    /// Returns whether the CPU supports the backend, and it was compiled in.
+    pub fn is_supported(self) -> bool {
+        match self {
+            Backend::Scalar => true,
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Sse2 => is_x86_feature_detected!("sse2"),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Avx2 => is_x86_feature_detected!("avx2"),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Avx512 => is_x86_feature_detected!("avx512f"),
+            #[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+            Backend::Neon => true,
+            #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+            Backend::Wasm128 => true,
+            _ => false,
+        }
+    }
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Self::detect()
+    }
+}
+
+/// The largest vector width of any backend. Spans shorter than a full vector are processed via
+/// temporary buffers of this size.
+const MAX_LANES: usize = 16;
+
+/// A vector of `f32` lanes.
+pub trait Simd:
+    Copy + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self>
+{
+    const LANES: usize;
+
+    /// Calls `f` with the target features of the backend enabled, so that the vector operations
+    /// inlined into it compile to the backend's instructions. Code which is not inlined into a
+    /// call to `vectorize` is compiled without them.
+    ///
+    /// # Safety
+    ///
+    /// The CPU must support the backend.
+    #[inline(always)]
+    unsafe fn vectorize<R>(f: impl FnOnce() -> R) -> R {
+        f()
+    }
+
+    fn splat(value: f32) -> Self;
+    /// Returns `[0.0, 1.0, 2.0, ...]`.
+    fn ramp() -> Self;
+
+    fn load(src: &[f32]) -> Self;
+    fn store(self, dst: &mut [f32]);
+
+    /// Loads fewer than `LANES` values, setting the remaining lanes to zero.
+    #[inline(always)]
+    fn load_partial(src: &[f32]) -> Self {
+        let mut buf = [0.0; MAX_LANES];
+        copy(src, &mut buf);
+        Self::load(&buf)
+    }
+
+    /// Stores fewer than `LANES` values.
+    #[inline(always)]
+    fn store_partial(self, dst: &mut [f32]) {
+        let mut buf = [0.0; MAX_LANES];
+        self.store(&mut buf);
+        copy(&buf, dst);
+    }
+
+    fn min(self, other: Self) -> Self;
+    fn max(self, other: Self) -> Self;
+    fn abs(self) -> Self;
+    fn floor(self) -> Self;
+    /// Rounds to the nearest integer, with ties rounding to even.
+    fn round(self) -> Self;
+    fn sqrt(self) -> Self;
+
+    /// Returns a mask with all bits set in the lanes where `self` is less than `other`, and all
+    /// bits clear elsewhere.
+    fn lt(self, other: Self) -> Self;
+    /// Chooses lanes from `if_true` where `mask` is set and from `if_false` elsewhere.
+    fn select(mask: Self, if_true: Self, if_false: Self) -> Self;
+
+    /// Computes the inclusive prefix sum of the lanes.
+    fn prefix_sum(self) -> Self;
+    fn last(self) -> f32;
+
+    /// Looks up each lane's index in the table. Indices are truncated and clamped to the bounds of
+    /// the table.
+    fn gather(table: &[f32], index: Self) -> Self;
+
+    /// Looks up the pixel at column `x` and row `y` for each lane, and unpacks it like `unpack`.
+    /// Coordinates are truncated and must lie within the image.
+    #[inline(always)]
+    fn gather_pixels(pixels: &[u32], stride: usize, x: Self, y: Self) -> [Self; 4] {
+        let mut xs = [0.0; MAX_LANES];
+        let mut ys = [0.0; MAX_LANES];
+        x.store(&mut xs);
+        y.store(&mut ys);
+
+        let mut buf = [0; MAX_LANES];
+        for ((pixel, &x), &y) in buf.iter_mut().zip(&xs).zip(&ys).take(Self::LANES) {
+            *pixel = pixels[y as usize * stride + x as usize];
+        }
+        Self::unpack(&buf)
+    }
+
+    /// Unpacks the four 8-bit channels of each pixel, from most to least significant byte, into
+    /// values between 0 and 255.
+    fn unpack(src: &[u32]) -> [Self; 4];
+    /// Packs four channels into 8-bit channels, from most to least significant byte, rounding to
+    /// nearest. Values must be between 0 and 255.
+    fn pack(channels: [Self; 4], dst: &mut [u32]);
+
+    /// Like `unpack`, but for fewer than `LANES` pixels.
+    #[inline(always)]
+    fn unpack_partial(src: &[u32]) -> [Self; 4] {
+        let mut buf = [0; MAX_LANES];
+        copy(src, &mut buf);
+        Self::unpack(&buf)
+    }
+
+    /// Like `pack`, but for fewer than `LANES` pixels.
+    #[inline(always)]
+    fn pack_partial(channels: [Self; 4], dst: &mut [u32]) {
+        let mut buf = [0; MAX_LANES];
+        Self::pack(channels, &mut buf);
+        copy(&buf, dst);
+    }
+
+    /// Like `unpack`, for pixels stored as four bytes which are read as little-endian `u32` values.
+    #[inline(always)]
+    fn unpack_bytes(src: &[[u8; 4]]) -> [Self; 4] {
+        let mut buf = [0; MAX_LANES];
+        for (pixel, &bytes) in buf.iter_mut().zip(src).take(Self::LANES) {
+            *pixel = u32::from_le_bytes(bytes);
+        }
+        Self::unpack(&buf)
+    }
+
+    /// Like `pack`, for pixels stored as four bytes which are written as little-endian `u32`
+    /// values.
+    #[inline(always)]
+    fn pack_bytes(channels: [Self; 4], dst: &mut [[u8; 4]]) {
+        let mut buf = [0; MAX_LANES];
+        Self::pack(channels, &mut buf);
+        for (bytes, &pixel) in dst.iter_mut().zip(&buf).take(Self::LANES) {
+            *bytes = pixel.to_le_bytes();
+        }
+    }
+
+    /// Like `unpack_bytes`, but for fewer than `LANES` pixels.
+    #[inline(always)]
+    fn unpack_bytes_partial(src: &[[u8; 4]]) -> [Self; 4] {
+        let mut buf = [[0; 4]; MAX_LANES];
+        copy(src, &mut buf);
+        Self::unpack_bytes(&buf)
+    }
+
+    /// Like `pack_bytes`, but for fewer than `LANES` pixels.
+    #[inline(always)]
+    fn pack_bytes_partial(channels: [Self; 4], dst: &mut [[u8; 4]]) {
+        let mut buf = [[0; 4]; MAX_LANES];
+        Self::pack_bytes(channels, &mut buf);
+        copy(&buf, dst);
+    }
+
+    /// Widens 8-bit values into lanes between 0 and 255.
+    fn unpack_u8(src: &[u8]) -> Self;
+
+    /// Like `unpack_u8`, but for fewer than `LANES` values.
+    #[inline(always)]
+    fn unpack_u8_partial(src: &[u8]) -> Self {
+        let mut buf = [0; MAX_LANES];
+        copy(src, &mut buf);
+        Self::unpack_u8(&buf)
+    }
+}
+
+/// Per-pixel 8-bit coverage values which are multiplied into the coverage of the area being
+/// composited, starting at its top left corner.
+#[derive(Copy, Clone)]
+pub struct ClipMask<'a> {
+    pub data: &'a [u8],
+    pub stride: usize,
+}
+
+/// Settings for running a pipeline.
+#[derive(Copy, Clone, Default)]
+pub struct Options<'a> {
+    /// The implementation to run. Must be supported by the CPU.
+    pub backend: Backend,
+    /// When set, colors are blended in linear light rather than directly on sRGB values.
+    pub linear: Option<&'a Srgb>,
+    /// When set, coverage values are remapped through this table, which is indexed by coverage
+    /// scaled to the table's length.
+    pub coverage: Option<&'a [f32]>,
+    /// Whether to dither colors before storing them to formats with limited precision.
+    pub dither: bool,
+}
+
+pub struct Pipeline<'a, S: Simd> {
+    shader: &'a Shader<'a>,
+    blend: Blend,
+    fill_rule: FillRule,
+    clip: Option<ClipMask<'a>>,
+    options: Options<'a>,
+    // The premultiplied source color, when every pixel is a solid color composited with plain
+    // source-over, so that the inner loops can skip shading and blend dispatch.
+    solid: Option<[f32; 4]>,
+    clip_row: usize,
+    y: f32,
+    accum: f32,
+    cvg: f32,
+    _simd: std::marker::PhantomData<S>,
+}
+
+impl<'a, S: Simd> Pipeline<'a, S> {
+    #[inline(always)]
+    pub fn new(
+        shader: &'a Shader<'a>,
+        blend: Blend,
+        fill_rule: FillRule,
+        clip: Option<ClipMask<'a>>,
+        options: Options<'a>,
+    ) -> Self {
+        let solid = match *shader {
+            Shader::Solid { premul, .. }
+                if blend == Blend::default() && options.linear.is_none() =>
+            {
+                Some(premul)
+            }
+            _ => None,
+        };
+
+        Pipeline {
+            shader,
+            blend,
+            fill_rule,
+            clip,
+            options,
+            solid,
+            clip_row: 0,
+            y: 0.0,
+            accum: 0.0,
+            cvg: 0.0,
+            _simd: std::marker::PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn reset(&mut self, y: usize) {
+        self.y = y as f32 + 0.5;
+        if let Some(clip) = self.clip {
+            self.clip_row = y * clip.stride;
+        }
+        self.accum = 0.0;
+        self.cvg = 0.0;
+    }
+
+    /// Adds coverage deltas to the running sum in `accum`, and returns the coverage values.
+    #[inline(always)]
+    fn accum(&self, accum: &mut f32, deltas: S) -> S {
+        let sum = S::splat(*accum) + deltas.prefix_sum();
+
+        let winding = match self.fill_rule {
+            FillRule::NonZero => sum,
+            FillRule::EvenOdd => sum - S::splat(2.0) * (S::splat(0.5) * sum).round(),
+        };
+        *accum = sum.last();
+        winding.abs().min(S::splat(1.0))
+    }
+
+    #[inline(always)]
+    fn blend<F: Format>(&self, x: usize, dst: [S; 4], mask: S) -> [S; 4] {
+        let src = self.shader.shade::<S>(x, self.y, F::CLAMPED);
+
+        if let Some(srgb) = self.options.linear {
+            return blend_linear(self.blend, srgb, src, dst, mask, F::CLAMPED);
+        }
+
+        if self.blend == Blend::default() {
+            return src_over(src, dst, mask);
+        }
+
+        blend_op(self.blend, src, dst, mask, F::CLAMPED)
+    }
+
+    /// Whether to dither the result of blending, which must be enabled and supported by the
+    /// format.
+    #[inline(always)]
+    fn dithers<F: Format>(&self) -> bool {
+        self.options.dither && F::DITHER_STEP != [0.0; 3]
+    }
+
+    /// Blends the source over `dst` and dithers the result, if enabled.
+    #[inline(always)]
+    fn blend_dither<F: Format>(&self, x: usize, dst: [S; 4], mask: S) -> [S; 4] {
+        let out = self.blend::<F>(x, dst, mask);
+        if self.dithers::<F>() {
+            return dither::dither(x, self.y as usize, F::DITHER_STEP, out);
+        }
+
+        out
+    }
+
+    /// Remaps coverage through the coverage table, if any.
+    #[inline(always)]
+    fn coverage(&self, mask: S) -> S {
+        match self.options.coverage {
+            Some(table) => {
+                let scale = (table.len() - 1) as f32;
+                S::gather(table, mask * S::splat(scale) + S::splat(0.5))
+            }
+            None => mask,
+        }
+    }
+
+    #[inline(always)]
+    pub fn fill<F: Format>(&mut self, x: usize, dst: &mut [F::Pixel]) {
+        let replaces = self.blend.mode == BlendMode::Normal
+            && (self.blend.op == CompositeOp::SrcOver || self.blend.op == CompositeOp::Src);
+        if self.cvg > 254.5 / 255.0 && replaces && self.clip.is_none() {
+            if let Some(color) = self.shader.opaque_color() {
+                dst.fill(F::opaque(color));
+                return;
+            }
+        }
+
+        if self.cvg > 0.5 / 255.0 {
+            let mask = self.coverage(S::splat(self.cvg));
+            let solid = self.solid.map(|premul| shader::clamp_premul(premul, F::CLAMPED));
+            match solid {
+                Some(src) if !self.dithers::<F>() && self.clip.is_none() => {
+                    // Coverage is the same for the whole span, so the source term of `src_over`
+                    // is computed once rather than for every chunk. Source-over is linear in the
+                    // destination, so it can work on scaled channels.
+                    let inv_a = S::splat(1.0) - mask * S::splat(src[0]);
+                    let src = map4(src, |c| mask * S::splat(c * F::SCALE));
+                    self.fill_with::<Scaled<F>>(
+                        x,
+                        dst,
+                        mask,
+                        #[inline(always)]
+                        |_, _, dst, _| map4([0, 1, 2, 3], |c| src[c] + inv_a * dst[c]),
+                    );
+                }
+                Some(src) if !self.dithers::<F>() => {
+                    self.fill_with::<Scaled<F>>(
+                        x,
+                        dst,
+                        mask,
+                        #[inline(always)]
+                        |_, _, dst, mask| src_over_scaled::<S, F>(src, dst, mask),
+                    );
+                }
+                _ => self.fill_with::<F>(x, dst, mask, Self::blend_dither::<F>),
+            }
+        }
+    }
+
+    /// The loop of `fill`, with the blending and dithering step given by `blend`. Choosing this
+    /// step once per span keeps the solid color loop free of shading, blend, and dither dispatch.
+    #[inline(always)]
+    fn fill_with<F: Format>(
+        &self,
+        x: usize,
+        dst: &mut [F::Pixel],
+        mask: S,
+        blend: impl Fn(&Self, usize, [S; 4], S) -> [S; 4],
+    ) {
+        let mut dst_chunks = dst.chunks_exact_mut(S::LANES);
+        let mut x = x;
+        for dst_chunk in &mut dst_chunks {
+            let mask = self.clip(x, S::LANES, mask);
+            let out = blend(self, x, F::unpack(dst_chunk), mask);
+            F::pack(out, dst_chunk);
+            x += S::LANES;
+        }
+
+        let dst_rem = dst_chunks.into_remainder();
+        if !dst_rem.is_empty() {
+            let mask = self.clip(x, dst_rem.len(), mask);
+            let out = blend(self, x, F::unpack_partial(dst_rem), mask);
+            F::pack_partial(out, dst_rem);
+        }
+    }
+
+    /// Multiplies coverage by the clip mask, if any, for the `len` pixels starting at `x`.
+    #[inline(always)]
+    fn clip(&self, x: usize, len: usize, mask: S) -> S {
+        match self.clip {
+            Some(clip) => {
+                let start = self.clip_row + x;
+                let data = &clip.data[start..start + len];
+                let alpha = if len == S::LANES {
+                    S::unpack_u8(data)
+                } else {
+                    S::unpack_u8_partial(data)
+                };
+                mask * alpha * S::splat(1.0 / 255.0)
+            }
+            None => mask,
+        }
+    }
+
+    /// Like `fill`, but for a span which is known to be fully covered.
+    #[inline(always)]
+    pub fn fill_covered<F: Format>(&mut self, x: usize, dst: &mut [F::Pixel]) {
+        self.cvg = 1.0;
+        self.fill::<F>(x, dst);
+    }
+
+    #[inline(always)]
+    pub fn fill_edge<F: Format>(&mut self, x: usize, dst: &mut [F::Pixel], cvg: &mut [f32]) {
+        match self.solid {
+            Some(src) if !self.dithers::<F>() => {
+                self.fill_edge_with::<Scaled<F>>(
+                    x,
+                    dst,
+                    cvg,
+                    #[inline(always)]
+                    |_, _, dst, mask| src_over_scaled::<S, F>(src, dst, mask),
+                );
+            }
+            _ => self.fill_edge_with::<F>(x, dst, cvg, Self::blend_dither::<F>),
+        }
+    }
+
+    /// The loop of `fill_edge`, with the blending and dithering step given by `blend`, like
+    /// `fill_with`.
+    #[inline(always)]
+    fn fill_edge_with<F: Format>(
+        &mut self,
+        x: usize,
+        dst: &mut [F::Pixel],
+        cvg: &mut [f32],
+        blend: impl Fn(&Self, usize, [S; 4], S) -> [S; 4],
+    ) {
+        // The running sums are kept in locals so that they stay in registers.
+        let mut accum = self.accum;
+        let mut last = S::splat(self.cvg);
+
+        let mut dst_chunks = dst.chunks_exact_mut(S::LANES);
+        let mut cvg_chunks = cvg.chunks_exact_mut(S::LANES);
+
+        let mut x = x;
+        for (dst_chunk, cvg_chunk) in (&mut dst_chunks).zip(&mut cvg_chunks) {
+            let mask = self.accum(&mut accum, S::load(cvg_chunk));
+            S::splat(0.0).store(cvg_chunk);
+            last = mask;
+            let mask = self.clip(x, S::LANES, self.coverage(mask));
+
+            let out = blend(self, x, F::unpack(dst_chunk), mask);
+            F::pack(out, dst_chunk);
+            x += S::LANES;
+        }
+
+        let dst_rem = dst_chunks.into_remainder();
+        let cvg_rem = cvg_chunks.into_remainder();
+        if !dst_rem.is_empty() {
+            let mask = self.accum(&mut accum, S::load_partial(cvg_rem));
+            S::splat(0.0).store_partial(cvg_rem);
+            last = mask;
+            let mask = self.clip(x, dst_rem.len(), self.coverage(mask));
+
+            let out = blend(self, x, F::unpack_partial(dst_rem), mask);
+            F::pack_partial(out, dst_rem);
+        }
+
+        self.accum = accum;
+        self.cvg = last.last();
+    }
+}
+
+/// Converts pixels from one format to another, clamping values to between 0 and 1.
+#[inline(always)]
+pub fn convert<S: Simd, Src: Format, Dst: Format>(src: &[Src::Pixel], dst: &mut [Dst::Pixel]) {
+    let clamp = |channels: [S; 4]| map4(channels, |c| c.max(S::splat(0.0)).min(S::splat(1.0)));
+
+    let mut src_chunks = src.chunks_exact(S::LANES);
+    let mut dst_chunks = dst.chunks_exact_mut(S::LANES);
+    for (src_chunk, dst_chunk) in (&mut src_chunks).zip(&mut dst_chunks) {
+        Dst::pack(clamp(Src::unpack::<S>(src_chunk)), dst_chunk);
+    }
+
+    let src_rem = src_chunks.remainder();
+    let dst_rem = dst_chunks.into_remainder();
+    if !dst_rem.is_empty() {
+        Dst::pack_partial(clamp(Src::unpack_partial::<S>(src_rem)), dst_rem);
+    }
+}
+
+/// Composites `src` over `dst`, interpolating between the destination and the result by coverage.
+#[inline(always)]
+fn src_over<S: Simd>(src: [S; 4], dst: [S; 4], mask: S) -> [S; 4] {
+    let [a, r, g, b] = src;
+    let [a_dst, r_dst, g_dst, b_dst] = dst;
+
+    let inv_a = S::splat(1.0) - mask * a;
+    let a_out = mask * a + inv_a * a_dst;
+    let r_out = mask * r + inv_a * r_dst;
+    let g_out = mask * g + inv_a * g_dst;
+    let b_out = mask * b + inv_a * b_dst;
+
+    [a_out, r_out, g_out, b_out]
+}
+
+/// Like `src_over`, for a solid color and destination channels scaled by `F::SCALE`.
+#[inline(always)]
+fn src_over_scaled<S: Simd, F: Format>(src: [f32; 4], dst: [S; 4], mask: S) -> [S; 4] {
+    let inv_a = S::splat(1.0) - mask * S::splat(src[0]);
+    map4([0, 1, 2, 3], |c| {
+        mask * S::splat(src[c] * F::SCALE) + inv_a * dst[c]
+    })
+}
+
+/// Like `Pipeline::blend`, but converting the source and destination to linear light first and
+/// the result back to sRGB afterwards.
+#[inline(never)]
+fn blend_linear<S: Simd>(
+    blend: Blend,
+    srgb: &Srgb,
+    src: [S; 4],
+    dst: [S; 4],
+    mask: S,
+    clamped: bool,
+) -> [S; 4] {
+    // SAFETY: Pipelines only run on backends which the CPU supports.
+    unsafe {
+        S::vectorize(
+            #[inline(always)]
+            || {
+                let src = srgb.to_linear(src, clamped);
+                let dst = srgb.to_linear(dst, clamped);
+
+                let out = if blend == Blend::default() {
+                    src_over(src, dst, mask)
+                } else {
+                    blend_op(blend, src, dst, mask, clamped)
+                };
+
+                srgb.to_srgb(out, clamped)
+            },
+        )
+    }
+}
+
+/// Blends and composites with anything other than normal source-over, interpolating between the
+/// destination and the result by coverage.
+// Kept out of line so that the source-over case, which is by far the most common, stays small.
+#[inline(never)]
+fn blend_op<S: Simd>(blend: Blend, src: [S; 4], dst: [S; 4], mask: S, clamped: bool) -> [S; 4] {
+    // SAFETY: Pipelines only run on backends which the CPU supports.
+    unsafe {
+        S::vectorize(
+            #[inline(always)]
+            || {
+                let src = blend::blend(blend.mode, src, dst);
+                let out = blend::composite(blend.op, src, dst, clamped);
+                map4([0, 1, 2, 3], |c| dst[c] + mask * (out[c] - dst[c]))
+            },
+        )
+    }
+}
+
+/// Like `array::map` for three elements. Unlike `array::map`, this is always inlined, so vector
+/// operations in `f` stay within `Simd::vectorize`.
+#[inline(always)]
+fn map3<T: Copy, U>(array: [T; 3], mut f: impl FnMut(T) -> U) -> [U; 3] {
+    [f(array[0]), f(array[1]), f(array[2])]
+}
+
+/// Like `map3`, for four elements.
+#[inline(always)]
+fn map4<T: Copy, U>(array: [T; 4], mut f: impl FnMut(T) -> U) -> [U; 4] {
+    [f(array[0]), f(array[1]), f(array[2]), f(array[3])]
+}
+
+// Copying element by element avoids calls to memcpy for the short spans handled by the partial
+// loads and stores.
+#[inline(always)]
+fn copy<T: Copy>(src: &[T], dst: &mut [T]) {
+    for (src, dst) in src.iter().zip(dst.iter_mut()) {
+        *dst = *src;
+    }
 }
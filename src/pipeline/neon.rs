@@ -1,138 +1,218 @@
 use std::arch::aarch64::*;
 
-use super::Pipeline;
-use crate::Color;
-
-pub struct Neon {
-    color: Color,
-    a: float32x4_t,
-    r: float32x4_t,
-    g: float32x4_t,
-    b: float32x4_t,
-    accum: f32,
-    cvg: f32,
-}
+use std::ops::{Add, Div, Mul, Sub};
+
+use super::Simd;
+
+#[derive(Copy, Clone)]
+pub struct Neon(float32x4_t);
+
+impl Simd for Neon {
+    const LANES: usize = 4;
 
-impl Neon {
     #[inline(always)]
-    fn accum(&mut self, deltas: float32x4_t) -> float32x4_t {
-        unsafe {
-            let zeros = vdupq_n_f32(0.0);
-            let sum1 = vaddq_f32(deltas, vextq_f32(zeros, deltas, 3));
-            let sum2 = vaddq_f32(sum1, vextq_f32(zeros, sum1, 2));
-            let sum = vaddq_f32(vdupq_n_f32(self.accum), sum2);
-            let mask = vminq_f32(vabsq_f32(sum), vdupq_n_f32(1.0));
+    fn splat(value: f32) -> Self {
+        unsafe { Neon(vdupq_n_f32(value)) }
+    }
 
-            self.accum = vgetq_lane_f32(sum, 3);
-            self.cvg = vgetq_lane_f32(mask, 3);
+    #[inline(always)]
+    fn ramp() -> Self {
+        unsafe { Neon(vld1q_f32([0.0, 1.0, 2.0, 3.0].as_ptr())) }
+    }
 
-            mask
+    #[inline(always)]
+    fn load(src: &[f32]) -> Self {
+        assert!(src.len() >= Self::LANES);
+        unsafe { Neon(vld1q_f32(src.as_ptr())) }
+    }
+
+    #[inline(always)]
+    fn store(self, dst: &mut [f32]) {
+        assert!(dst.len() >= Self::LANES);
+        unsafe { vst1q_f32(dst.as_mut_ptr(), self.0) }
+    }
+
+    #[inline(always)]
+    fn min(self, other: Self) -> Self {
+        unsafe { Neon(vminq_f32(self.0, other.0)) }
+    }
+
+    #[inline(always)]
+    fn max(self, other: Self) -> Self {
+        unsafe { Neon(vmaxq_f32(self.0, other.0)) }
+    }
+
+    #[inline(always)]
+    fn abs(self) -> Self {
+        unsafe { Neon(vabsq_f32(self.0)) }
+    }
+
+    #[inline(always)]
+    fn floor(self) -> Self {
+        unsafe { Neon(vrndmq_f32(self.0)) }
+    }
+
+    #[inline(always)]
+    fn round(self) -> Self {
+        unsafe { Neon(vrndnq_f32(self.0)) }
+    }
+
+    #[inline(always)]
+    fn sqrt(self) -> Self {
+        unsafe { Neon(vsqrtq_f32(self.0)) }
+    }
+
+    #[inline(always)]
+    fn lt(self, other: Self) -> Self {
+        unsafe { Neon(vreinterpretq_f32_u32(vcltq_f32(self.0, other.0))) }
+    }
+
+    #[inline(always)]
+    fn select(mask: Self, if_true: Self, if_false: Self) -> Self {
+        unsafe {
+            Neon(vbslq_f32(
+                vreinterpretq_u32_f32(mask.0),
+                if_true.0,
+                if_false.0,
+            ))
         }
     }
 
     #[inline(always)]
-    fn blend(&self, dst: uint32x4_t, mask: float32x4_t) -> uint32x4_t {
+    fn prefix_sum(self) -> Self {
         unsafe {
-            let a_dst = vcvtq_f32_u32(vshrq_n_u32(dst, 24));
-            let r_dst = vcvtq_f32_u32(vandq_u32(vshrq_n_u32(dst, 16), vdupq_n_u32(0xFF)));
-            let g_dst = vcvtq_f32_u32(vandq_u32(vshrq_n_u32(dst, 8), vdupq_n_u32(0xFF)));
-            let b_dst = vcvtq_f32_u32(vandq_u32(dst, vdupq_n_u32(0xFF)));
-
-            let inv_a = vfmsq_f32(vdupq_n_f32(1.0), mask, vmulq_n_f32(self.a, 1.0 / 255.0));
-            let a_out = vfmaq_f32(vmulq_f32(self.a, mask), inv_a, a_dst);
-            let r_out = vfmaq_f32(vmulq_f32(self.r, mask), inv_a, r_dst);
-            let g_out = vfmaq_f32(vmulq_f32(self.g, mask), inv_a, g_dst);
-            let b_out = vfmaq_f32(vmulq_f32(self.b, mask), inv_a, b_dst);
-
-            let out = vshlq_n_u32(vcvtq_u32_f32(a_out), 24);
-            let out = vorrq_u32(out, vshlq_n_u32(vcvtq_u32_f32(r_out), 16));
-            let out = vorrq_u32(out, vshlq_n_u32(vcvtq_u32_f32(g_out), 8));
-            let out = vorrq_u32(out, vcvtq_u32_f32(b_out));
-
-            out
+            let zeros = vdupq_n_f32(0.0);
+            let sum1 = vaddq_f32(self.0, vextq_f32(zeros, self.0, 3));
+            Neon(vaddq_f32(sum1, vextq_f32(zeros, sum1, 2)))
         }
     }
-}
 
-impl Pipeline for Neon {
     #[inline(always)]
-    fn build(color: Color) -> Self {
-        let a_unit = color.a() as f32 * (1.0 / 255.0);
+    fn last(self) -> f32 {
+        unsafe { vgetq_lane_f32(self.0, 3) }
+    }
 
+    #[inline(always)]
+    fn gather(table: &[f32], index: Self) -> Self {
         unsafe {
-            Neon {
-                color,
-                a: vdupq_n_f32(color.a() as f32),
-                r: vdupq_n_f32(a_unit * color.r() as f32),
-                g: vdupq_n_f32(a_unit * color.g() as f32),
-                b: vdupq_n_f32(a_unit * color.b() as f32),
-                accum: 0.0,
-                cvg: 0.0,
-            }
+            let max = vdupq_n_f32((table.len() - 1) as f32);
+            let clamped = vminq_f32(vmaxq_f32(index.0, vdupq_n_f32(0.0)), max);
+
+            let mut indices = [0u32; 4];
+            vst1q_u32(indices.as_mut_ptr(), vcvtq_u32_f32(clamped));
+
+            let values = [
+                *table.get_unchecked(indices[0] as usize),
+                *table.get_unchecked(indices[1] as usize),
+                *table.get_unchecked(indices[2] as usize),
+                *table.get_unchecked(indices[3] as usize),
+            ];
+            Neon(vld1q_f32(values.as_ptr()))
         }
     }
 
     #[inline(always)]
-    fn reset(&mut self) {
-        self.accum = 0.0;
-        self.cvg = 0.0;
+    fn unpack(src: &[u32]) -> [Self; 4] {
+        assert!(src.len() >= Self::LANES);
+        unsafe { unpack(vld1q_u32(src.as_ptr())) }
     }
 
     #[inline(always)]
-    fn fill(&mut self, dst: &mut [u32]) {
-        if self.cvg > 254.5 / 255.0 && self.color.a() == 255 {
-            dst.fill(self.color.into());
-        } else if self.cvg > 0.5 / 255.0 {
-            unsafe {
-                let mut dst_chunks = dst.chunks_exact_mut(4);
+    fn pack(channels: [Self; 4], dst: &mut [u32]) {
+        assert!(dst.len() >= Self::LANES);
+        unsafe { vst1q_u32(dst.as_mut_ptr(), pack(channels)) }
+    }
 
-                for dst_chunk in &mut dst_chunks {
-                    let mask = vdupq_n_f32(self.cvg);
-                    let dst = vld1q_u32(dst_chunk.as_ptr());
-                    let out = self.blend(dst, mask);
-                    vst1q_u32(dst_chunk.as_mut_ptr(), out);
-                }
+    // Reinterpreting bytes as `u32` lanes only gives little-endian values on little-endian targets.
+    // Big-endian targets use the default implementations.
 
-                let dst_rem = dst_chunks.into_remainder();
-                for pixel in dst_rem {
-                    let mask = vdupq_n_f32(self.cvg);
-                    let dst = vld1q_lane_u32(pixel, vdupq_n_u32(0), 0);
-                    let out = self.blend(dst, mask);
-                    vst1q_lane_u32(pixel, out, 0);
-                }
-            }
+    #[cfg(target_endian = "little")]
+    #[inline(always)]
+    fn unpack_bytes(src: &[[u8; 4]]) -> [Self; 4] {
+        assert!(src.len() >= Self::LANES);
+        unsafe { unpack(vreinterpretq_u32_u8(vld1q_u8(src.as_ptr() as *const u8))) }
+    }
+
+    #[cfg(target_endian = "little")]
+    #[inline(always)]
+    fn pack_bytes(channels: [Self; 4], dst: &mut [[u8; 4]]) {
+        assert!(dst.len() >= Self::LANES);
+        unsafe {
+            vst1q_u8(
+                dst.as_mut_ptr() as *mut u8,
+                vreinterpretq_u8_u32(pack(channels)),
+            )
         }
     }
 
     #[inline(always)]
-    fn fill_edge(&mut self, dst: &mut [u32], cvg: &mut [f32]) {
-        let mut dst_chunks = dst.chunks_exact_mut(4);
-        let mut cvg_chunks = cvg.chunks_exact_mut(4);
+    fn unpack_u8(src: &[u8]) -> Self {
+        assert!(src.len() >= Self::LANES);
+        unsafe {
+            // `vcreate_u8` fills lanes from the least significant byte, on either endianness.
+            let bytes = u32::from_le_bytes([src[0], src[1], src[2], src[3]]);
+            let words = vmovl_u8(vcreate_u8(bytes as u64));
+            Neon(vcvtq_f32_u32(vmovl_u16(vget_low_u16(words))))
+        }
+    }
+}
 
-        for (dst_chunk, cvg_chunk) in (&mut dst_chunks).zip(&mut cvg_chunks) {
-            unsafe {
-                let deltas = vld1q_f32(cvg_chunk.as_ptr());
-                let mask = self.accum(deltas);
-                vst1q_f32(cvg_chunk.as_mut_ptr(), vdupq_n_f32(0.0));
+#[inline(always)]
+unsafe fn unpack(pixels: uint32x4_t) -> [Neon; 4] {
+    let byte_mask = vdupq_n_u32(0xFF);
+    let unpack = |channel: uint32x4_t| Neon(vcvtq_f32_u32(channel));
+
+    [
+        unpack(vshrq_n_u32(pixels, 24)),
+        unpack(vandq_u32(vshrq_n_u32(pixels, 16), byte_mask)),
+        unpack(vandq_u32(vshrq_n_u32(pixels, 8), byte_mask)),
+        unpack(vandq_u32(pixels, byte_mask)),
+    ]
+}
 
-                let dst = vld1q_u32(dst_chunk.as_ptr());
-                let out = self.blend(dst, mask);
-                vst1q_u32(dst_chunk.as_mut_ptr(), out);
-            }
-        }
+#[inline(always)]
+unsafe fn pack(channels: [Neon; 4]) -> uint32x4_t {
+    let pack = |channel: Neon| vcvtnq_u32_f32(channel.0);
+    let [a, r, g, b] = channels.map(pack);
 
-        let dst_rem = dst_chunks.into_remainder();
-        let cvg_rem = cvg_chunks.into_remainder();
-        for (pixel, delta) in std::iter::zip(dst_rem, cvg_rem) {
-            unsafe {
-                let deltas = vld1q_lane_f32(delta, vdupq_n_f32(0.0), 0);
-                let mask = self.accum(deltas);
-                vst1q_lane_f32(delta, vdupq_n_f32(0.0), 0);
-
-                let dst = vld1q_lane_u32(pixel, vdupq_n_u32(0), 0);
-                let out = self.blend(dst, mask);
-                vst1q_lane_u32(pixel, out, 0);
-            }
-        }
+    let out = vshlq_n_u32(a, 24);
+    let out = vorrq_u32(out, vshlq_n_u32(r, 16));
+    let out = vorrq_u32(out, vshlq_n_u32(g, 8));
+    vorrq_u32(out, b)
+}
+
+impl Add for Neon {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        unsafe { Neon(vaddq_f32(self.0, rhs.0)) }
+    }
+}
+
+impl Sub for Neon {
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        unsafe { Neon(vsubq_f32(self.0, rhs.0)) }
+    }
+}
+
+impl Mul for Neon {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, rhs: Self) -> Self {
+        unsafe { Neon(vmulq_f32(self.0, rhs.0)) }
+    }
+}
+
+impl Div for Neon {
+    type Output = Self;
+
+    #[inline(always)]
+    fn div(self, rhs: Self) -> Self {
+        unsafe { Neon(vdivq_f32(self.0, rhs.0)) }
     }
 }
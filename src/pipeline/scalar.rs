@@ -1,75 +1,142 @@
-use super::Pipeline;
-use crate::Color;
-
-pub struct Scalar {
-    color: Color,
-    a: f32,
-    r: f32,
-    g: f32,
-    b: f32,
-    accum: f32,
-    cvg: f32,
-}
+use std::ops::{Add, Div, Mul, Sub};
+
+use super::Simd;
+
+#[derive(Copy, Clone)]
+pub struct Scalar(f32);
+
+impl Simd for Scalar {
+    const LANES: usize = 1;
 
-impl Scalar {
     #[inline(always)]
-    fn blend(&self, dst: u32) -> u32 {
-        let a_dst = (dst >> 24) as f32;
-        let r_dst = ((dst >> 16) & 0xFF) as f32;
-        let g_dst = ((dst >> 8) & 0xFF) as f32;
-        let b_dst = (dst & 0xFF) as f32;
+    fn splat(value: f32) -> Self {
+        Scalar(value)
+    }
 
-        let inv_a = 1.0 - self.cvg * (1.0 / 255.0) * self.a;
-        let a_out = self.cvg * self.a + inv_a * a_dst;
-        let r_out = self.cvg * self.r + inv_a * r_dst;
-        let g_out = self.cvg * self.g + inv_a * g_dst;
-        let b_out = self.cvg * self.b + inv_a * b_dst;
+    #[inline(always)]
+    fn ramp() -> Self {
+        Scalar(0.0)
+    }
 
-        (a_out as u32) << 24 | (r_out as u32) << 16 | (g_out as u32) << 8 | b_out as u32
+    #[inline(always)]
+    fn load(src: &[f32]) -> Self {
+        Scalar(src[0])
     }
-}
 
-impl Pipeline for Scalar {
     #[inline(always)]
-    fn build(color: Color) -> Self {
-        let a_unit = color.a() as f32 * (1.0 / 255.0);
+    fn store(self, dst: &mut [f32]) {
+        dst[0] = self.0;
+    }
 
-        Scalar {
-            color,
-            a: color.a() as f32,
-            r: a_unit * color.r() as f32,
-            g: a_unit * color.g() as f32,
-            b: a_unit * color.b() as f32,
-            accum: 0.0,
-            cvg: 0.0,
-        }
+    #[inline(always)]
+    fn min(self, other: Self) -> Self {
+        Scalar(self.0.min(other.0))
     }
 
     #[inline(always)]
-    fn reset(&mut self) {
-        self.accum = 0.0;
-        self.cvg = 0.0;
+    fn max(self, other: Self) -> Self {
+        Scalar(self.0.max(other.0))
     }
 
     #[inline(always)]
-    fn fill(&mut self, dst: &mut [u32]) {
-        if self.cvg > 254.5 / 255.0 && self.color.a() == 255 {
-            dst.fill(self.color.into());
-        } else if self.cvg > 0.5 / 255.0 {
-            for pixel in dst {
-                *pixel = self.blend(*pixel);
-            }
-        }
+    fn abs(self) -> Self {
+        Scalar(self.0.abs())
     }
 
     #[inline(always)]
-    fn fill_edge(&mut self, dst: &mut [u32], cvg: &mut [f32]) {
-        for (pixel, delta) in std::iter::zip(dst, cvg) {
-            self.accum += *delta;
-            self.cvg = self.accum.abs().min(1.0);
-            *delta = 0.0;
+    fn floor(self) -> Self {
+        Scalar(self.0.floor())
+    }
 
-            *pixel = self.blend(*pixel);
+    #[inline(always)]
+    fn round(self) -> Self {
+        Scalar(self.0.round_ties_even())
+    }
+
+    #[inline(always)]
+    fn sqrt(self) -> Self {
+        Scalar(self.0.sqrt())
+    }
+
+    #[inline(always)]
+    fn lt(self, other: Self) -> Self {
+        Scalar(f32::from_bits(if self.0 < other.0 { !0 } else { 0 }))
+    }
+
+    #[inline(always)]
+    fn select(mask: Self, if_true: Self, if_false: Self) -> Self {
+        if mask.0.to_bits() != 0 {
+            if_true
+        } else {
+            if_false
         }
     }
+
+    #[inline(always)]
+    fn prefix_sum(self) -> Self {
+        self
+    }
+
+    #[inline(always)]
+    fn last(self) -> f32 {
+        self.0
+    }
+
+    #[inline(always)]
+    fn gather(table: &[f32], index: Self) -> Self {
+        Scalar(table[(index.0 as usize).min(table.len() - 1)])
+    }
+
+    #[inline(always)]
+    fn unpack(src: &[u32]) -> [Self; 4] {
+        let pixel = src[0];
+        [24, 16, 8, 0].map(|shift| Scalar(((pixel >> shift) & 0xFF) as f32))
+    }
+
+    #[inline(always)]
+    fn pack(channels: [Self; 4], dst: &mut [u32]) {
+        let [a, r, g, b] = channels.map(|c| c.0.round_ties_even() as u32);
+        dst[0] = a << 24 | r << 16 | g << 8 | b;
+    }
+
+    #[inline(always)]
+    fn unpack_u8(src: &[u8]) -> Self {
+        Scalar(src[0] as f32)
+    }
+}
+
+impl Add for Scalar {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        Scalar(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Scalar {
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        Scalar(self.0 - rhs.0)
+    }
+}
+
+impl Mul for Scalar {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, rhs: Self) -> Self {
+        Scalar(self.0 * rhs.0)
+    }
+}
+
+impl Div for Scalar {
+    type Output = Self;
+
+    #[inline(always)]
+    fn div(self, rhs: Self) -> Self {
+        Scalar(self.0 / rhs.0)
+    }
 }
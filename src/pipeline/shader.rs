@@ -0,0 +1,576 @@
+use std::f32::consts::TAU;
+
+use super::format::{from_bytes, Format, RgbaF32};
+use super::{map3, map4, Simd};
+use crate::image::{Filter, Image};
+use crate::paint::{Extend, Gradient, GradientKind, Paint, Pattern, Stop};
+use crate::{Affine, Color, ColorF32, PremulColor};
+
+/// The number of entries in the color lookup table of a gradient.
+const LUT_SIZE: usize = 256;
+
+/// The largest distance between the focal point and the center of a radial gradient, as a
+/// fraction of the radius.
+const MAX_FOCAL_DISTANCE: f32 = 0.99;
+
+/// A paint prepared for evaluation at pixel centers.
+pub enum Shader<'a> {
+    Solid {
+        // The color, if it is fully opaque and has 8-bit channels.
+        opaque: Option<Color>,
+        // Premultiplied components in the order a, r, g, b.
+        premul: [f32; 4],
+    },
+    Linear {
+        // Coefficients mapping a pixel position (x, y) to the gradient parameter.
+        coeffs: [f32; 3],
+        extend: Extend,
+        lut: Vec<f32>,
+    },
+    Radial {
+        // Coefficients mapping a pixel position (x, y) to the position relative to the focal
+        // point, in units of the radius.
+        coeffs: [f32; 6],
+        // The center relative to the focal point, in units of the radius.
+        center: [f32; 2],
+        // The reciprocal of 1 - |center|^2.
+        scale: f32,
+        extend: Extend,
+        lut: Vec<f32>,
+    },
+    Sweep {
+        // Coefficients mapping a pixel position (x, y) to the position relative to the center,
+        // rotated such that the start angle lies on the positive x axis.
+        coeffs: [f32; 6],
+        // The reciprocal of the angle spanned by the gradient, in turns.
+        scale: f32,
+        extend: Extend,
+        lut: Vec<f32>,
+    },
+    Pattern {
+        // Coefficients mapping a pixel position (x, y) to image coordinates.
+        coeffs: [f32; 6],
+        image: Image<'a>,
+        extend: Extend,
+        filter: Filter,
+        opacity: f32,
+    },
+    Layer {
+        // Premultiplied r, g, b, and a values of rows of `width` pixels, `stride` pixels apart,
+        // which line up with the pixels being drawn.
+        data: &'a [f32],
+        width: usize,
+        stride: usize,
+        opacity: f32,
+    },
+}
+
+impl<'a> Shader<'a> {
+    /// Prepares a paint for drawing, where `transform` maps from the paint's coordinate space to
+    /// pixel coordinates. Returns `None` if the paint would not draw anything.
+    pub fn new(paint: &Paint<'a>, transform: Affine) -> Option<Shader<'a>> {
+        match *paint {
+            Paint::Color(color) => Some(Shader::solid(color)),
+            Paint::ColorF32(color) => Some(Shader::solid_f32(color)),
+            Paint::Gradient(gradient) => Shader::gradient(gradient, transform),
+            Paint::Pattern(pattern) => Shader::pattern(pattern, transform),
+        }
+    }
+
+    fn solid(color: Color) -> Shader<'a> {
+        let a = color.a() as f32 * (1.0 / 255.0);
+
+        Shader::Solid {
+            opaque: Some(color).filter(|color| color.a() == 255),
+            premul: [
+                a,
+                a * (1.0 / 255.0) * color.r() as f32,
+                a * (1.0 / 255.0) * color.g() as f32,
+                a * (1.0 / 255.0) * color.b() as f32,
+            ],
+        }
+    }
+
+    fn solid_f32(color: ColorF32) -> Shader<'a> {
+        Shader::Solid {
+            opaque: None,
+            premul: color.premultiplied(),
+        }
+    }
+
+    /// A solid color which is already premultiplied.
+    pub fn premultiplied(color: PremulColor) -> Shader<'a> {
+        Shader::Solid {
+            opaque: Some(color.unpremultiply()).filter(|color| color.a() == 255),
+            premul: [color.a(), color.r(), color.g(), color.b()].map(|c| c as f32 * (1.0 / 255.0)),
+        }
+    }
+
+    fn gradient(gradient: &Gradient, transform: Affine) -> Option<Shader<'a>> {
+        let last = gradient.stops.last()?;
+        if gradient.stops.len() == 1 {
+            return Some(Shader::solid_f32(last.color));
+        }
+
+        let inverse = (transform * gradient.transform).invert()?;
+
+        match gradient.kind {
+            GradientKind::Linear { start, end } => {
+                // A degenerate gradient is painted with the color of the last stop.
+                let dir = end - start;
+                let len_squared = dir.dot(dir);
+                if len_squared == 0.0 {
+                    return Some(Shader::solid_f32(last.color));
+                }
+
+                // Project onto the gradient vector, such that start maps to 0 and end maps to 1.
+                let scale = 1.0 / len_squared;
+                let project = Affine::new([
+                    scale * dir.x,
+                    scale * dir.y,
+                    -scale * start.dot(dir),
+                    0.0,
+                    0.0,
+                    0.0,
+                ]);
+                let [a, b, c, ..] = (project * inverse).coeffs();
+
+                Some(Shader::Linear {
+                    coeffs: [a, b, c],
+                    extend: gradient.extend,
+                    lut: build_lut(&gradient.stops),
+                })
+            }
+            GradientKind::Radial {
+                center,
+                radius,
+                focal,
+            } => {
+                // A degenerate gradient is painted with the color of the last stop.
+                if !radius.is_finite() || radius <= 0.0 {
+                    return Some(Shader::solid_f32(last.color));
+                }
+
+                // Keep the focal point strictly inside the circle, so that every point is covered
+                // by exactly one circle.
+                let mut offset = (1.0 / radius) * (center - focal);
+                let distance = offset.length();
+                if distance > MAX_FOCAL_DISTANCE {
+                    offset *= MAX_FOCAL_DISTANCE / distance;
+                }
+                let focal = center - radius * offset;
+
+                let scale = 1.0 / radius;
+                let normalize =
+                    Affine::new([scale, 0.0, -scale * focal.x, 0.0, scale, -scale * focal.y]);
+
+                Some(Shader::Radial {
+                    coeffs: (normalize * inverse).coeffs(),
+                    center: [offset.x, offset.y],
+                    scale: 1.0 / (1.0 - offset.dot(offset)),
+                    extend: gradient.extend,
+                    lut: build_lut(&gradient.stops),
+                })
+            }
+            GradientKind::Sweep {
+                center,
+                start_angle,
+                end_angle,
+            } => {
+                // A degenerate gradient is painted with the color of the last stop.
+                let span = (end_angle - start_angle) * (1.0 / TAU);
+                if !span.is_finite() || span == 0.0 {
+                    return Some(Shader::solid_f32(last.color));
+                }
+
+                // Mirror the angles when the end angle is smaller than the start angle, so that the
+                // gradient always runs from the start angle towards the end angle.
+                let direction = Affine::new([1.0, 0.0, 0.0, 0.0, span.signum(), 0.0]);
+                let normalize = direction
+                    * Affine::rotate(start_angle)
+                    * Affine::translate(-center.x, -center.y);
+
+                Some(Shader::Sweep {
+                    coeffs: (normalize * inverse).coeffs(),
+                    scale: 1.0 / span.abs(),
+                    extend: gradient.extend,
+                    lut: build_lut(&gradient.stops),
+                })
+            }
+        }
+    }
+
+    fn pattern(pattern: Pattern<'a>, transform: Affine) -> Option<Shader<'a>> {
+        Shader::image(
+            pattern.image,
+            transform * pattern.transform,
+            pattern.extend,
+            pattern.filter,
+            1.0,
+        )
+    }
+
+    /// Prepares an image for drawing, where `transform` maps from image coordinates to pixel
+    /// coordinates, and the sampled colors are scaled by `opacity`.
+    pub fn image(
+        image: Image<'a>,
+        transform: Affine,
+        extend: Extend,
+        filter: Filter,
+        opacity: f32,
+    ) -> Option<Shader<'a>> {
+        if image.width() == 0 || image.height() == 0 || opacity.is_nan() || opacity <= 0.0 {
+            return None;
+        }
+
+        Some(Shader::Pattern {
+            coeffs: transform.invert()?.coeffs(),
+            image,
+            extend,
+            filter,
+            opacity: opacity.min(1.0),
+        })
+    }
+
+    /// Prepares float pixels for drawing at the same positions, with colors scaled by `opacity`.
+    /// Unlike images, the pixels are neither rounded nor clamped.
+    pub fn layer(
+        data: &'a [f32],
+        width: usize,
+        height: usize,
+        stride: usize,
+        opacity: f32,
+    ) -> Option<Shader<'a>> {
+        if width == 0 || height == 0 || opacity.is_nan() || opacity <= 0.0 {
+            return None;
+        }
+        assert!(stride >= width);
+        assert!(data.len() >= 4 * ((height - 1) * stride + width));
+
+        Some(Shader::Layer {
+            data,
+            width,
+            stride,
+            opacity: opacity.min(1.0),
+        })
+    }
+
+    /// Returns the color of the shader if it is solid and fully opaque.
+    #[inline(always)]
+    pub fn opaque_color(&self) -> Option<Color> {
+        match *self {
+            Shader::Solid { opaque, .. } => opaque,
+            _ => None,
+        }
+    }
+
+    /// Evaluates the premultiplied a, r, g, and b components at the pixels starting at (x, y).
+    /// For formats which are `clamped` to 8 bits per channel, colors are limited to alpha and
+    /// gradients take the nearest entry of their lookup tables. Otherwise, gradients interpolate
+    /// between entries, and float colors may exceed 1.
+    #[inline(always)]
+    pub fn shade<S: Simd>(&self, x: usize, y: f32, clamped: bool) -> [S; 4] {
+        match *self {
+            Shader::Solid { premul, .. } => map4(clamp_premul(premul, clamped), S::splat),
+            Shader::Linear {
+                coeffs,
+                extend,
+                ref lut,
+            } => {
+                let x = S::ramp() + S::splat(x as f32 + 0.5);
+                let t = S::splat(coeffs[0]) * x + S::splat(coeffs[1] * y + coeffs[2]);
+                lookup(lut, apply_extend(extend, t), clamped)
+            }
+            Shader::Radial {
+                coeffs,
+                center,
+                scale,
+                extend,
+                ref lut,
+            } => {
+                let x = S::ramp() + S::splat(x as f32 + 0.5);
+                let px = S::splat(coeffs[0]) * x + S::splat(coeffs[1] * y + coeffs[2]);
+                let py = S::splat(coeffs[3]) * x + S::splat(coeffs[4] * y + coeffs[5]);
+
+                // Solve |p - t * center| = t for the largest t, where p is the position relative
+                // to the focal point.
+                let b = px * S::splat(center[0]) + py * S::splat(center[1]);
+                let c = px * px + py * py;
+                let discriminant = b * b + S::splat(1.0 / scale) * c;
+                let t = (discriminant.sqrt() - b) * S::splat(scale);
+
+                lookup(lut, apply_extend(extend, t), clamped)
+            }
+            Shader::Sweep {
+                coeffs,
+                scale,
+                extend,
+                ref lut,
+            } => {
+                let x = S::ramp() + S::splat(x as f32 + 0.5);
+                let px = S::splat(coeffs[0]) * x + S::splat(coeffs[1] * y + coeffs[2]);
+                let py = S::splat(coeffs[3]) * x + S::splat(coeffs[4] * y + coeffs[5]);
+
+                let t = turns(px, py) * S::splat(scale);
+                lookup(lut, apply_extend(extend, t), clamped)
+            }
+            Shader::Pattern {
+                coeffs,
+                ref image,
+                extend,
+                filter,
+                opacity,
+            } => {
+                let x = S::ramp() + S::splat(x as f32 + 0.5);
+                let u = S::splat(coeffs[0]) * x + S::splat(coeffs[1] * y + coeffs[2]);
+                let v = S::splat(coeffs[3]) * x + S::splat(coeffs[4] * y + coeffs[5]);
+
+                map4(filter_image(image, extend, filter, u, v), |c| {
+                    c * S::splat(opacity)
+                })
+            }
+            Shader::Layer {
+                data,
+                width,
+                stride,
+                opacity,
+            } => {
+                let start = y as usize * stride + x;
+                let len = (width - x).min(S::LANES);
+                let pixels = &data.as_chunks::<4>().0[start..start + len];
+                map4(RgbaF32::unpack::<S>(pixels), |c| c * S::splat(opacity))
+            }
+        }
+    }
+}
+
+/// Samples an image at the image coordinates (u, v).
+// Patterns are expensive to evaluate anyway, so keeping this out of line avoids bloating the
+// compositing loops of the other shaders.
+#[inline(never)]
+fn filter_image<S: Simd>(image: &Image, extend: Extend, filter: Filter, u: S, v: S) -> [S; 4] {
+    // SAFETY: Pipelines only run on backends which the CPU supports.
+    unsafe {
+        S::vectorize(
+            #[inline(always)]
+            || {
+                match filter {
+                    Filter::Nearest => {
+                        let one = S::splat(1.0);
+                        sample(image, extend, u.floor(), v.floor(), [one], [one])
+                    }
+                    Filter::Bilinear => {
+                        // Measure positions relative to pixel centers.
+                        let u = u - S::splat(0.5);
+                        let v = v - S::splat(0.5);
+                        let x0 = u.floor();
+                        let y0 = v.floor();
+                        let fx = u - x0;
+                        let fy = v - y0;
+
+                        let one = S::splat(1.0);
+                        sample(image, extend, x0, y0, [one - fx, fx], [one - fy, fy])
+                    }
+                    Filter::Bicubic => {
+                        let u = u - S::splat(0.5);
+                        let v = v - S::splat(0.5);
+                        let x0 = u.floor();
+                        let y0 = v.floor();
+                        let wx = cubic_weights(u - x0);
+                        let wy = cubic_weights(v - y0);
+
+                        let one = S::splat(1.0);
+                        let [a, r, g, b] = sample(image, extend, x0 - one, y0 - one, wx, wy);
+
+                        // The filter has negative lobes, so the result can overshoot.
+                        let a = a.max(S::splat(0.0)).min(one);
+                        let [r, g, b] = map3([r, g, b], |c| c.max(S::splat(0.0)).min(a));
+                        [a, r, g, b]
+                    }
+                }
+            },
+        )
+    }
+}
+
+/// Sums the pixels in an `N` by `N` block starting at column `x0` and row `y0`, weighted by the
+/// products of the given column and row weights.
+#[inline(always)]
+fn sample<S: Simd, const N: usize>(
+    image: &Image,
+    extend: Extend,
+    x0: S,
+    y0: S,
+    wx: [S; N],
+    wy: [S; N],
+) -> [S; 4] {
+    let mut xs = [S::splat(0.0); N];
+    for (i, x) in xs.iter_mut().enumerate() {
+        *x = extend_coord(extend, x0 + S::splat(i as f32), image.width());
+    }
+
+    let mut out = [S::splat(0.0); 4];
+    for (j, &wy) in wy.iter().enumerate() {
+        let y = extend_coord(extend, y0 + S::splat(j as f32), image.height());
+
+        let mut row = [S::splat(0.0); 4];
+        for (&x, &wx) in xs.iter().zip(wx.iter()) {
+            let pixel = S::gather_pixels(image.data(), image.stride(), x, y);
+            for (row, pixel) in row.iter_mut().zip(pixel) {
+                *row = *row + wx * pixel;
+            }
+        }
+
+        for (out, row) in out.iter_mut().zip(row) {
+            *out = *out + wy * row;
+        }
+    }
+
+    from_bytes(out)
+}
+
+/// Maps an integer pixel coordinate into the range `0..size` according to the extend mode.
+#[inline(always)]
+fn extend_coord<S: Simd>(extend: Extend, coord: S, size: usize) -> S {
+    let size = size as f32;
+    let coord = match extend {
+        Extend::Pad => coord,
+        Extend::Repeat => coord - S::splat(size) * (coord / S::splat(size)).floor(),
+        Extend::Reflect => {
+            let period = 2.0 * size;
+            let coord = coord - S::splat(period) * (coord / S::splat(period)).floor();
+            coord.min(S::splat(period - 1.0) - coord)
+        }
+    };
+
+    // Clamping also guards against rounding errors in the cases above.
+    coord.max(S::splat(0.0)).min(S::splat(size - 1.0))
+}
+
+/// Computes the weights of the four pixels around a position for the Mitchell-Netravali filter
+/// (B = C = 1/3), given the fractional offset of the position from the second pixel.
+#[inline(always)]
+fn cubic_weights<S: Simd>(t: S) -> [S; 4] {
+    // The filter as a function of the distance x, for x < 1 and for 1 <= x < 2.
+    let near = |x: S| (S::splat(7.0 / 6.0) * x - S::splat(2.0)) * x * x + S::splat(8.0 / 9.0);
+    let far = |x: S| {
+        ((S::splat(-7.0 / 18.0) * x + S::splat(2.0)) * x - S::splat(10.0 / 3.0)) * x
+            + S::splat(16.0 / 9.0)
+    };
+
+    let one = S::splat(1.0);
+    [far(one + t), near(t), near(one - t), far(S::splat(2.0) - t)]
+}
+
+/// Computes the angle of (x, y) in turns, between 0 and 1.
+#[inline(always)]
+fn turns<S: Simd>(x: S, y: S) -> S {
+    let zero = S::splat(0.0);
+    let x_abs = x.abs();
+    let y_abs = y.abs();
+
+    // Approximate the arctangent of the ratio between 0 and 1 with a polynomial, which has a
+    // maximum error of about 1e-5 radians.
+    let max = x_abs.max(y_abs).max(S::splat(f32::MIN_POSITIVE));
+    let ratio = x_abs.min(y_abs) / max;
+    let squared = ratio * ratio;
+    let poly = (S::splat(-0.046496475 / TAU) * squared + S::splat(0.15931422 / TAU)) * squared
+        - S::splat(0.32762277 / TAU);
+    let angle = poly * squared * ratio + S::splat(1.0 / TAU) * ratio;
+
+    // Unfold the angle from the first octant to the full circle.
+    let angle = S::select(x_abs.lt(y_abs), S::splat(0.25) - angle, angle);
+    let angle = S::select(x.lt(zero), S::splat(0.5) - angle, angle);
+    let angle = S::select(y.lt(zero), S::splat(1.0) - angle, angle);
+
+    // Angles just below a full turn may round up to exactly 1.
+    S::select(angle.lt(S::splat(1.0)), angle, zero)
+}
+
+#[inline(always)]
+fn apply_extend<S: Simd>(extend: Extend, t: S) -> S {
+    match extend {
+        Extend::Pad => t.max(S::splat(0.0)).min(S::splat(1.0)),
+        Extend::Repeat => t - t.floor(),
+        Extend::Reflect => {
+            let period = (S::splat(0.5) * t + S::splat(0.5)).floor();
+            (t - S::splat(2.0) * period).abs()
+        }
+    }
+}
+
+/// Limits premultiplied color channels to alpha if `clamped` is set.
+#[inline(always)]
+pub fn clamp_premul(premul: [f32; 4], clamped: bool) -> [f32; 4] {
+    let [a, r, g, b] = premul;
+    if clamped {
+        [a, r.min(a), g.min(a), b.min(a)]
+    } else {
+        premul
+    }
+}
+
+/// Looks up the color at `t` in a gradient table. If `clamped` is set, this takes the nearest entry
+/// and limits colors to alpha, and otherwise it interpolates linearly between the two nearest
+/// entries.
+#[inline(always)]
+fn lookup<S: Simd>(lut: &[f32], t: S, clamped: bool) -> [S; 4] {
+    let index = S::splat((LUT_SIZE - 1) as f32) * t;
+    let planes = [0, 1, 2, 3].map(|c| &lut[c * LUT_SIZE..(c + 1) * LUT_SIZE]);
+
+    if clamped {
+        let index = index + S::splat(0.5);
+        let [a, r, g, b] = map4(planes, |plane| S::gather(plane, index));
+        return [a, r.min(a), g.min(a), b.min(a)];
+    }
+
+    // The entry after the last one is clamped to it, which has no weight at t = 1.
+    let lower = index.floor();
+    let factor = index - lower;
+    let upper = lower + S::splat(1.0);
+    map4(planes, |plane| {
+        let low = S::gather(plane, lower);
+        low + factor * (S::gather(plane, upper) - low)
+    })
+}
+
+/// Builds a table of premultiplied colors sampled at evenly spaced positions between 0 and 1,
+/// stored as separate a, r, g, and b planes.
+fn build_lut(stops: &[Stop]) -> Vec<f32> {
+    let mut offsets = Vec::with_capacity(stops.len());
+    let mut prev_offset = 0.0f32;
+    for stop in stops {
+        let offset = stop.offset.max(prev_offset).min(1.0);
+        offsets.push(offset);
+        prev_offset = offset;
+    }
+
+    let mut lut = vec![0.0; 4 * LUT_SIZE];
+    let mut index = 0;
+    for i in 0..LUT_SIZE {
+        let t = i as f32 * (1.0 / (LUT_SIZE - 1) as f32);
+
+        while index + 1 < stops.len() && offsets[index + 1] <= t {
+            index += 1;
+        }
+
+        let [a, r, g, b] = if index + 1 < stops.len() {
+            let start = stops[index].color.channels();
+            let end = stops[index + 1].color.channels();
+
+            let span = offsets[index + 1] - offsets[index];
+            let factor = ((t - offsets[index]) / span).max(0.0);
+
+            [0, 1, 2, 3].map(|c| start[c] + factor * (end[c] - start[c]))
+        } else {
+            stops[index].color.channels()
+        };
+
+        lut[i] = a;
+        lut[LUT_SIZE + i] = a * r;
+        lut[2 * LUT_SIZE + i] = a * g;
+        lut[3 * LUT_SIZE + i] = a * b;
+    }
+
+    lut
+}
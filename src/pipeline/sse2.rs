@@ -3,144 +3,285 @@ use std::arch::x86::*;
 #[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::*;
 
-use super::Pipeline;
-use crate::Color;
-
-pub struct Sse2 {
-    color: Color,
-    a: __m128,
-    r: __m128,
-    g: __m128,
-    b: __m128,
-    accum: f32,
-    cvg: f32,
-}
+use std::ops::{Add, Div, Mul, Sub};
+
+use super::{map4, Simd};
+
+#[derive(Copy, Clone)]
+pub struct Sse2(__m128);
+
+impl Simd for Sse2 {
+    const LANES: usize = 4;
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn vectorize<R>(f: impl FnOnce() -> R) -> R {
+        f()
+    }
 
-impl Sse2 {
     #[inline(always)]
-    fn accum(&mut self, deltas: __m128) -> __m128 {
+    fn splat(value: f32) -> Self {
+        unsafe { Sse2(_mm_set1_ps(value)) }
+    }
+
+    #[inline(always)]
+    fn ramp() -> Self {
+        unsafe { Sse2(_mm_setr_ps(0.0, 1.0, 2.0, 3.0)) }
+    }
+
+    #[inline(always)]
+    fn load(src: &[f32]) -> Self {
+        assert!(src.len() >= Self::LANES);
+        unsafe { Sse2(_mm_loadu_ps(src.as_ptr())) }
+    }
+
+    #[inline(always)]
+    fn store(self, dst: &mut [f32]) {
+        assert!(dst.len() >= Self::LANES);
+        unsafe { _mm_storeu_ps(dst.as_mut_ptr(), self.0) }
+    }
+
+    #[inline(always)]
+    fn min(self, other: Self) -> Self {
+        unsafe { Sse2(_mm_min_ps(self.0, other.0)) }
+    }
+
+    #[inline(always)]
+    fn max(self, other: Self) -> Self {
+        unsafe { Sse2(_mm_max_ps(self.0, other.0)) }
+    }
+
+    #[inline(always)]
+    fn abs(self) -> Self {
         unsafe {
-            let shifted = _mm_castsi128_ps(_mm_slli_si128(_mm_castps_si128(deltas), 4));
-            let sum1 = _mm_add_ps(deltas, shifted);
-            let shifted = _mm_castsi128_ps(_mm_slli_si128(_mm_castps_si128(sum1), 8));
-            let sum2 = _mm_add_ps(sum1, shifted);
-            let sum = _mm_add_ps(_mm_set1_ps(self.accum), sum2);
+            Sse2(_mm_and_ps(
+                self.0,
+                _mm_castsi128_ps(_mm_set1_epi32(!(1 << 31))),
+            ))
+        }
+    }
 
-            let abs = _mm_and_ps(sum, _mm_castsi128_ps(_mm_set1_epi32(!(1 << 31))));
-            let mask = _mm_min_ps(abs, _mm_set1_ps(1.0));
+    #[inline(always)]
+    fn floor(self) -> Self {
+        // SSE2 has no floor instruction, so truncate and then correct negative values.
+        unsafe {
+            let truncated = _mm_cvtepi32_ps(_mm_cvttps_epi32(self.0));
+            let correction = _mm_and_ps(_mm_cmplt_ps(self.0, truncated), _mm_set1_ps(1.0));
+            Sse2(_mm_sub_ps(truncated, correction))
+        }
+    }
 
-            self.accum = _mm_cvtss_f32(_mm_shuffle_ps(sum, sum, 0x03));
-            self.cvg = _mm_cvtss_f32(_mm_shuffle_ps(mask, mask, 0x03));
+    #[inline(always)]
+    fn round(self) -> Self {
+        unsafe { Sse2(_mm_cvtepi32_ps(_mm_cvtps_epi32(self.0))) }
+    }
+
+    #[inline(always)]
+    fn sqrt(self) -> Self {
+        unsafe { Sse2(_mm_sqrt_ps(self.0)) }
+    }
 
-            mask
+    #[inline(always)]
+    fn lt(self, other: Self) -> Self {
+        unsafe { Sse2(_mm_cmplt_ps(self.0, other.0)) }
+    }
+
+    #[inline(always)]
+    fn select(mask: Self, if_true: Self, if_false: Self) -> Self {
+        unsafe {
+            Sse2(_mm_or_ps(
+                _mm_and_ps(mask.0, if_true.0),
+                _mm_andnot_ps(mask.0, if_false.0),
+            ))
         }
     }
 
     #[inline(always)]
-    fn blend(&self, dst: __m128i, mask: __m128) -> __m128i {
+    fn prefix_sum(self) -> Self {
         unsafe {
-            let byte_mask = _mm_set1_epi32(0xFF);
-            let a_dst = _mm_cvtepi32_ps(_mm_srli_epi32(dst, 24));
-            let r_dst = _mm_cvtepi32_ps(_mm_and_si128(_mm_srli_epi32(dst, 16), byte_mask));
-            let g_dst = _mm_cvtepi32_ps(_mm_and_si128(_mm_srli_epi32(dst, 8), byte_mask));
-            let b_dst = _mm_cvtepi32_ps(_mm_and_si128(dst, byte_mask));
-
-            let a_unit = _mm_mul_ps(self.a, _mm_set1_ps(1.0 / 255.0));
-            let inv_a = _mm_sub_ps(_mm_set1_ps(1.0), _mm_mul_ps(mask, a_unit));
-            let a_out = _mm_add_ps(_mm_mul_ps(self.a, mask), _mm_mul_ps(inv_a, a_dst));
-            let r_out = _mm_add_ps(_mm_mul_ps(self.r, mask), _mm_mul_ps(inv_a, r_dst));
-            let g_out = _mm_add_ps(_mm_mul_ps(self.g, mask), _mm_mul_ps(inv_a, g_dst));
-            let b_out = _mm_add_ps(_mm_mul_ps(self.b, mask), _mm_mul_ps(inv_a, b_dst));
-
-            let out = _mm_slli_epi32(_mm_cvtps_epi32(a_out), 24);
-            let out = _mm_or_si128(out, _mm_slli_epi32(_mm_cvtps_epi32(r_out), 16));
-            let out = _mm_or_si128(out, _mm_slli_epi32(_mm_cvtps_epi32(g_out), 8));
-            let out = _mm_or_si128(out, _mm_cvtps_epi32(b_out));
-
-            out
+            let shifted = _mm_castsi128_ps(_mm_slli_si128(_mm_castps_si128(self.0), 4));
+            let sum1 = _mm_add_ps(self.0, shifted);
+            let shifted = _mm_castsi128_ps(_mm_slli_si128(_mm_castps_si128(sum1), 8));
+            Sse2(_mm_add_ps(sum1, shifted))
         }
     }
-}
 
-impl Pipeline for Sse2 {
     #[inline(always)]
-    fn build(color: Color) -> Self {
+    fn last(self) -> f32 {
+        unsafe { _mm_cvtss_f32(_mm_shuffle_ps(self.0, self.0, 0xFF)) }
+    }
+
+    #[inline(always)]
+    fn gather(table: &[f32], index: Self) -> Self {
         unsafe {
-            let a_unit = color.a() as f32 * (1.0 / 255.0);
-
-            Sse2 {
-                color,
-                a: _mm_set1_ps(color.a() as f32),
-                r: _mm_set1_ps(a_unit * color.r() as f32),
-                g: _mm_set1_ps(a_unit * color.g() as f32),
-                b: _mm_set1_ps(a_unit * color.b() as f32),
-                accum: 0.0,
-                cvg: 0.0,
-            }
+            let max = _mm_set1_ps((table.len() - 1) as f32);
+            let clamped = _mm_min_ps(_mm_max_ps(index.0, _mm_setzero_ps()), max);
+
+            let mut indices = [0i32; 4];
+            _mm_storeu_si128(
+                indices.as_mut_ptr() as *mut __m128i,
+                _mm_cvttps_epi32(clamped),
+            );
+
+            Sse2(_mm_setr_ps(
+                *table.get_unchecked(indices[0] as usize),
+                *table.get_unchecked(indices[1] as usize),
+                *table.get_unchecked(indices[2] as usize),
+                *table.get_unchecked(indices[3] as usize),
+            ))
         }
     }
 
     #[inline(always)]
-    fn reset(&mut self) {
-        self.accum = 0.0;
-        self.cvg = 0.0;
+    fn unpack(src: &[u32]) -> [Self; 4] {
+        assert!(src.len() >= Self::LANES);
+        unsafe { unpack(_mm_loadu_si128(src.as_ptr() as *const __m128i)) }
     }
 
     #[inline(always)]
-    fn fill(&mut self, dst: &mut [u32]) {
-        if self.cvg > 254.5 / 255.0 && self.color.a() == 255 {
-            dst.fill(self.color.into());
-        } else if self.cvg > 0.5 / 255.0 {
-            unsafe {
-                let mut dst_chunks = dst.chunks_exact_mut(4);
+    fn pack(channels: [Self; 4], dst: &mut [u32]) {
+        assert!(dst.len() >= Self::LANES);
+        unsafe { _mm_storeu_si128(dst.as_mut_ptr() as *mut __m128i, pack(channels)) }
+    }
 
-                for dst_chunk in &mut dst_chunks {
-                    let mask = _mm_set1_ps(self.cvg);
-                    let dst = _mm_loadu_si128(dst_chunk.as_ptr() as *const __m128i);
-                    let out = self.blend(dst, mask);
-                    _mm_storeu_si128(dst_chunk.as_mut_ptr() as *mut __m128i, out);
-                }
+    #[inline(always)]
+    fn unpack_bytes(src: &[[u8; 4]]) -> [Self; 4] {
+        assert!(src.len() >= Self::LANES);
+        unsafe { unpack(_mm_loadu_si128(src.as_ptr() as *const __m128i)) }
+    }
+
+    #[inline(always)]
+    fn pack_bytes(channels: [Self; 4], dst: &mut [[u8; 4]]) {
+        assert!(dst.len() >= Self::LANES);
+        unsafe { _mm_storeu_si128(dst.as_mut_ptr() as *mut __m128i, pack(channels)) }
+    }
+
+    #[inline(always)]
+    fn unpack_bytes_partial(src: &[[u8; 4]]) -> [Self; 4] {
+        unsafe { unpack(load_partial(src.as_ptr() as *const u32, src.len())) }
+    }
 
-                let dst_rem = dst_chunks.into_remainder();
-                for pixel in dst_rem {
-                    let mask = _mm_set1_ps(self.cvg);
-                    let dst = _mm_loadu_si32(pixel as *const u32 as *const u8);
-                    let out = self.blend(dst, mask);
-                    _mm_storeu_si32(pixel as *mut u32 as *mut u8, out);
-                }
-            }
+    #[inline(always)]
+    fn pack_bytes_partial(channels: [Self; 4], dst: &mut [[u8; 4]]) {
+        unsafe { store_partial(dst.as_mut_ptr() as *mut u32, dst.len(), pack(channels)) }
+    }
+
+    #[inline(always)]
+    fn unpack_u8(src: &[u8]) -> Self {
+        assert!(src.len() >= Self::LANES);
+        unsafe {
+            let bytes = _mm_cvtsi32_si128((src.as_ptr() as *const i32).read_unaligned());
+            let zero = _mm_setzero_si128();
+            let words = _mm_unpacklo_epi8(bytes, zero);
+            Sse2(_mm_cvtepi32_ps(_mm_unpacklo_epi16(words, zero)))
         }
     }
 
     #[inline(always)]
-    fn fill_edge(&mut self, dst: &mut [u32], cvg: &mut [f32]) {
-        let mut dst_chunks = dst.chunks_exact_mut(4);
-        let mut cvg_chunks = cvg.chunks_exact_mut(4);
+    fn load_partial(src: &[f32]) -> Self {
+        unsafe {
+            let bits = load_partial(src.as_ptr() as *const u32, src.len());
+            Sse2(_mm_castsi128_ps(bits))
+        }
+    }
+
+    #[inline(always)]
+    fn unpack_partial(src: &[u32]) -> [Self; 4] {
+        unsafe { unpack(load_partial(src.as_ptr(), src.len())) }
+    }
 
-        for (dst_chunk, cvg_chunk) in (&mut dst_chunks).zip(&mut cvg_chunks) {
-            unsafe {
-                let deltas = _mm_loadu_ps(cvg_chunk.as_ptr());
-                let mask = self.accum(deltas);
-                _mm_storeu_ps(cvg_chunk.as_mut_ptr(), _mm_setzero_ps());
+    #[inline(always)]
+    fn pack_partial(channels: [Self; 4], dst: &mut [u32]) {
+        unsafe { store_partial(dst.as_mut_ptr(), dst.len(), pack(channels)) }
+    }
+}
 
-                let dst = _mm_loadu_si128(dst_chunk.as_ptr() as *const __m128i);
-                let out = self.blend(dst, mask);
-                _mm_storeu_si128(dst_chunk.as_mut_ptr() as *mut __m128i, out);
-            }
+/// Loads fewer than 4 32-bit values, setting the remaining lanes to zero. `src` doesn't need to be
+/// aligned.
+#[inline(always)]
+unsafe fn load_partial(src: *const u32, len: usize) -> __m128i {
+    match len {
+        1 => _mm_cvtsi32_si128(src.read_unaligned() as i32),
+        2 => _mm_loadl_epi64(src as *const __m128i),
+        3 => {
+            let low = _mm_loadl_epi64(src as *const __m128i);
+            _mm_unpacklo_epi64(low, _mm_cvtsi32_si128(src.add(2).read_unaligned() as i32))
         }
+        _ => _mm_setzero_si128(),
+    }
+}
 
-        let dst_rem = dst_chunks.into_remainder();
-        let cvg_rem = cvg_chunks.into_remainder();
-        for (pixel, delta) in std::iter::zip(dst_rem, cvg_rem) {
-            unsafe {
-                let deltas = _mm_load_ss(delta);
-                let mask = self.accum(deltas);
-                _mm_store_ss(delta, _mm_set_ss(0.0));
-
-                let dst = _mm_loadu_si32(pixel as *const u32 as *const u8);
-                let out = self.blend(dst, mask);
-                _mm_storeu_si32(pixel as *mut u32 as *mut u8, out);
-            }
+/// Stores the first `len` lanes, where `len` is less than 4. `dst` doesn't need to be aligned.
+#[inline(always)]
+unsafe fn store_partial(dst: *mut u32, len: usize, values: __m128i) {
+    match len {
+        1 => dst.write_unaligned(_mm_cvtsi128_si32(values) as u32),
+        2 => _mm_storel_epi64(dst as *mut __m128i, values),
+        3 => {
+            _mm_storel_epi64(dst as *mut __m128i, values);
+            let high = _mm_cvtsi128_si32(_mm_unpackhi_epi64(values, values)) as u32;
+            dst.add(2).write_unaligned(high);
         }
+        _ => {}
+    }
+}
+
+#[inline(always)]
+unsafe fn unpack(pixels: __m128i) -> [Sse2; 4] {
+    let byte_mask = _mm_set1_epi32(0xFF);
+    let unpack = |channel: __m128i| Sse2(_mm_cvtepi32_ps(channel));
+
+    [
+        unpack(_mm_srli_epi32(pixels, 24)),
+        unpack(_mm_and_si128(_mm_srli_epi32(pixels, 16), byte_mask)),
+        unpack(_mm_and_si128(_mm_srli_epi32(pixels, 8), byte_mask)),
+        unpack(_mm_and_si128(pixels, byte_mask)),
+    ]
+}
+
+#[inline(always)]
+unsafe fn pack(channels: [Sse2; 4]) -> __m128i {
+    let pack = |channel: Sse2| _mm_cvtps_epi32(channel.0);
+    let [a, r, g, b] = map4(channels, pack);
+
+    let out = _mm_slli_epi32(a, 24);
+    let out = _mm_or_si128(out, _mm_slli_epi32(r, 16));
+    let out = _mm_or_si128(out, _mm_slli_epi32(g, 8));
+    _mm_or_si128(out, b)
+}
+
+impl Add for Sse2 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        unsafe { Sse2(_mm_add_ps(self.0, rhs.0)) }
+    }
+}
+
+impl Sub for Sse2 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        unsafe { Sse2(_mm_sub_ps(self.0, rhs.0)) }
+    }
+}
+
+impl Mul for Sse2 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, rhs: Self) -> Self {
+        unsafe { Sse2(_mm_mul_ps(self.0, rhs.0)) }
+    }
+}
+
+impl Div for Sse2 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn div(self, rhs: Self) -> Self {
+        unsafe { Sse2(_mm_div_ps(self.0, rhs.0)) }
     }
 }
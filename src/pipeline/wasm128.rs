@@ -0,0 +1,197 @@
+use std::arch::wasm32::*;
+
+use std::ops::{Add, Div, Mul, Sub};
+
+use super::{map4, Simd};
+
+#[derive(Copy, Clone)]
+pub struct Wasm128(v128);
+
+impl Simd for Wasm128 {
+    const LANES: usize = 4;
+
+    #[inline(always)]
+    fn splat(value: f32) -> Self {
+        Wasm128(f32x4_splat(value))
+    }
+
+    #[inline(always)]
+    fn ramp() -> Self {
+        Wasm128(f32x4(0.0, 1.0, 2.0, 3.0))
+    }
+
+    #[inline(always)]
+    fn load(src: &[f32]) -> Self {
+        assert!(src.len() >= Self::LANES);
+        unsafe { Wasm128(v128_load(src.as_ptr() as *const v128)) }
+    }
+
+    #[inline(always)]
+    fn store(self, dst: &mut [f32]) {
+        assert!(dst.len() >= Self::LANES);
+        unsafe { v128_store(dst.as_mut_ptr() as *mut v128, self.0) }
+    }
+
+    // The pseudo-minimum and maximum map to single instructions on most hosts, unlike `f32x4_min`
+    // and `f32x4_max`, which have to propagate NaNs.
+    #[inline(always)]
+    fn min(self, other: Self) -> Self {
+        Wasm128(f32x4_pmin(self.0, other.0))
+    }
+
+    #[inline(always)]
+    fn max(self, other: Self) -> Self {
+        Wasm128(f32x4_pmax(self.0, other.0))
+    }
+
+    #[inline(always)]
+    fn abs(self) -> Self {
+        Wasm128(f32x4_abs(self.0))
+    }
+
+    #[inline(always)]
+    fn floor(self) -> Self {
+        Wasm128(f32x4_floor(self.0))
+    }
+
+    #[inline(always)]
+    fn round(self) -> Self {
+        Wasm128(f32x4_nearest(self.0))
+    }
+
+    #[inline(always)]
+    fn sqrt(self) -> Self {
+        Wasm128(f32x4_sqrt(self.0))
+    }
+
+    #[inline(always)]
+    fn lt(self, other: Self) -> Self {
+        Wasm128(f32x4_lt(self.0, other.0))
+    }
+
+    #[inline(always)]
+    fn select(mask: Self, if_true: Self, if_false: Self) -> Self {
+        Wasm128(v128_bitselect(if_true.0, if_false.0, mask.0))
+    }
+
+    #[inline(always)]
+    fn prefix_sum(self) -> Self {
+        let zeros = f32x4_splat(0.0);
+        let sum1 = f32x4_add(self.0, i32x4_shuffle::<0, 4, 5, 6>(zeros, self.0));
+        Wasm128(f32x4_add(sum1, i32x4_shuffle::<0, 1, 4, 5>(zeros, sum1)))
+    }
+
+    #[inline(always)]
+    fn last(self) -> f32 {
+        f32x4_extract_lane::<3>(self.0)
+    }
+
+    #[inline(always)]
+    fn gather(table: &[f32], index: Self) -> Self {
+        let max = f32x4_splat((table.len() - 1) as f32);
+        let clamped = f32x4_pmin(f32x4_pmax(index.0, f32x4_splat(0.0)), max);
+        let indices = u32x4_trunc_sat_f32x4(clamped);
+
+        unsafe {
+            Wasm128(f32x4(
+                *table.get_unchecked(u32x4_extract_lane::<0>(indices) as usize),
+                *table.get_unchecked(u32x4_extract_lane::<1>(indices) as usize),
+                *table.get_unchecked(u32x4_extract_lane::<2>(indices) as usize),
+                *table.get_unchecked(u32x4_extract_lane::<3>(indices) as usize),
+            ))
+        }
+    }
+
+    #[inline(always)]
+    fn unpack(src: &[u32]) -> [Self; 4] {
+        assert!(src.len() >= Self::LANES);
+        unpack(unsafe { v128_load(src.as_ptr() as *const v128) })
+    }
+
+    #[inline(always)]
+    fn pack(channels: [Self; 4], dst: &mut [u32]) {
+        assert!(dst.len() >= Self::LANES);
+        unsafe { v128_store(dst.as_mut_ptr() as *mut v128, pack(channels)) }
+    }
+
+    #[inline(always)]
+    fn unpack_bytes(src: &[[u8; 4]]) -> [Self; 4] {
+        assert!(src.len() >= Self::LANES);
+        unpack(unsafe { v128_load(src.as_ptr() as *const v128) })
+    }
+
+    #[inline(always)]
+    fn pack_bytes(channels: [Self; 4], dst: &mut [[u8; 4]]) {
+        assert!(dst.len() >= Self::LANES);
+        unsafe { v128_store(dst.as_mut_ptr() as *mut v128, pack(channels)) }
+    }
+
+    #[inline(always)]
+    fn unpack_u8(src: &[u8]) -> Self {
+        assert!(src.len() >= Self::LANES);
+        let bytes = unsafe { v128_load32_zero(src.as_ptr() as *const u32) };
+        let words = u16x8_extend_low_u8x16(bytes);
+        Wasm128(f32x4_convert_u32x4(u32x4_extend_low_u16x8(words)))
+    }
+}
+
+#[inline(always)]
+fn unpack(pixels: v128) -> [Wasm128; 4] {
+    let byte_mask = u32x4_splat(0xFF);
+    let unpack = |channel: v128| Wasm128(f32x4_convert_u32x4(channel));
+
+    [
+        unpack(u32x4_shr(pixels, 24)),
+        unpack(v128_and(u32x4_shr(pixels, 16), byte_mask)),
+        unpack(v128_and(u32x4_shr(pixels, 8), byte_mask)),
+        unpack(v128_and(pixels, byte_mask)),
+    ]
+}
+
+#[inline(always)]
+fn pack(channels: [Wasm128; 4]) -> v128 {
+    // Round to nearest before truncating, like the conversions of the other backends.
+    let pack = |channel: Wasm128| u32x4_trunc_sat_f32x4(f32x4_nearest(channel.0));
+    let [a, r, g, b] = map4(channels, pack);
+
+    let out = u32x4_shl(a, 24);
+    let out = v128_or(out, u32x4_shl(r, 16));
+    let out = v128_or(out, u32x4_shl(g, 8));
+    v128_or(out, b)
+}
+
+impl Add for Wasm128 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        Wasm128(f32x4_add(self.0, rhs.0))
+    }
+}
+
+impl Sub for Wasm128 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        Wasm128(f32x4_sub(self.0, rhs.0))
+    }
+}
+
+impl Mul for Wasm128 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, rhs: Self) -> Self {
+        Wasm128(f32x4_mul(self.0, rhs.0))
+    }
+}
+
+impl Div for Wasm128 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn div(self, rhs: Self) -> Self {
+        Wasm128(f32x4_div(self.0, rhs.0))
+    }
+}
@@ -0,0 +1,45 @@
+/// Determines whether color channels are stored multiplied by alpha.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AlphaMode {
+    Premultiplied,
+    Straight,
+}
+
+/// The pixels of a render target, in one of the supported formats. Rows are stored one after
+/// another without padding.
+#[derive(Debug)]
+pub enum Pixels<'a> {
+    /// Native-endian `0xAARRGGBB` values with premultiplied alpha. This is the format used by
+    /// `Renderer::attach`.
+    Argb32(&'a mut [u32]),
+    /// Four bytes per pixel, in R, G, B, A order.
+    Rgba8(&'a mut [u8], AlphaMode),
+    /// Four bytes per pixel, in B, G, R, A order.
+    Bgra8(&'a mut [u8], AlphaMode),
+    /// One byte of alpha per pixel.
+    A8(&'a mut [u8]),
+    /// Native-endian 16-bit values with 5 bits of red, 6 bits of green, and 5 bits of blue, from
+    /// most to least significant. Alpha is discarded.
+    Rgb565(&'a mut [u16]),
+    /// Four `f32` values per pixel, in R, G, B, A order, with premultiplied alpha. Values are not
+    /// rounded or clamped between blends, so they may exceed 1. Layers are stored as floats as
+    /// well, while masks are stored as 8-bit coverage like clips.
+    RgbaF32(&'a mut [f32]),
+}
+
+impl<'a> Pixels<'a> {
+    /// Returns the number of pixels.
+    pub fn len(&self) -> usize {
+        match *self {
+            Pixels::Argb32(ref data) => data.len(),
+            Pixels::Rgba8(ref data, _) | Pixels::Bgra8(ref data, _) => data.len() / 4,
+            Pixels::A8(ref data) => data.len(),
+            Pixels::Rgb565(ref data) => data.len(),
+            Pixels::RgbaF32(ref data) => data.len() / 4,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
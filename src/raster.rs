@@ -1,8 +1,9 @@
 use std::mem;
 
 use crate::flatten::Line;
-use crate::pipeline::{self, Pipeline};
-use crate::{geom::Point, Color};
+use crate::pipeline::{self, Backend, ClipMask, Format, Options, Pipeline, Shader, Simd};
+use crate::pipeline::{Argb32, Bgra8, Rgb565, Rgba8, RgbaF32, A8};
+use crate::{geom::Point, AlphaMode, Blend, FillRule, Pixels};
 
 const BITS_PER_BITMASK: usize = u64::BITS as usize;
 const BITS_PER_BITMASK_SHIFT: usize = BITS_PER_BITMASK.trailing_zeros() as usize;
@@ -13,6 +14,118 @@ const PIXELS_PER_BIT_SHIFT: usize = PIXELS_PER_BIT.trailing_zeros() as usize;
 const PIXELS_PER_BITMASK: usize = PIXELS_PER_BIT * BITS_PER_BITMASK;
 const PIXELS_PER_BITMASK_SHIFT: usize = PIXELS_PER_BITMASK.trailing_zeros() as usize;
 
+/// Evaluates `$body` with `$format` naming the pipeline format of `$pixels`, and `$data` bound to
+/// its pixels starting at index `$start`. Prefixing `$pixels` with `mut` binds them mutably.
+macro_rules! with_format {
+    (@match $pixels:expr, ($($mode:tt)*), ($($borrow:tt)*), $chunks:ident, $start:expr,
+     $format:ident, $data:ident, $body:expr) => {
+        match *$pixels {
+            Pixels::Argb32($($mode)* data) => {
+                type $format = Argb32<false>;
+                let $data = $($borrow)* data[$start..];
+                $body
+            }
+            Pixels::Rgba8($($mode)* data, AlphaMode::Premultiplied) => {
+                type $format = Rgba8<false>;
+                let $data = $($borrow)* data.$chunks().0[$start..];
+                $body
+            }
+            Pixels::Rgba8($($mode)* data, AlphaMode::Straight) => {
+                type $format = Rgba8<true>;
+                let $data = $($borrow)* data.$chunks().0[$start..];
+                $body
+            }
+            Pixels::Bgra8($($mode)* data, AlphaMode::Premultiplied) => {
+                type $format = Bgra8<false>;
+                let $data = $($borrow)* data.$chunks().0[$start..];
+                $body
+            }
+            Pixels::Bgra8($($mode)* data, AlphaMode::Straight) => {
+                type $format = Bgra8<true>;
+                let $data = $($borrow)* data.$chunks().0[$start..];
+                $body
+            }
+            Pixels::A8($($mode)* data) => {
+                type $format = A8;
+                let $data = $($borrow)* data[$start..];
+                $body
+            }
+            Pixels::Rgb565($($mode)* data) => {
+                type $format = Rgb565;
+                let $data = $($borrow)* data[$start..];
+                $body
+            }
+            Pixels::RgbaF32($($mode)* data) => {
+                type $format = RgbaF32;
+                let $data = $($borrow)* data.$chunks().0[$start..];
+                $body
+            }
+        }
+    };
+    (mut $pixels:expr, $start:expr, |$format:ident, $data:ident| $body:expr) => {
+        with_format!(@match $pixels, (ref mut), (&mut), as_chunks_mut, $start, $format, $data, $body)
+    };
+    ($pixels:expr, $start:expr, |$format:ident, $data:ident| $body:expr) => {
+        with_format!(@match $pixels, (ref), (&), as_chunks, $start, $format, $data, $body)
+    };
+}
+
+/// Evaluates `$body` with `$simd` naming the pipeline implementation for `$backend`, inside a call
+/// to `Simd::vectorize`. Backends which weren't compiled in fall back to `Scalar`.
+macro_rules! with_backend {
+    ($backend:expr, |$simd:ident| $body:expr) => {
+        match $backend {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Avx512 => {
+                type $simd = pipeline::Avx512;
+                // SAFETY: Backends are checked to be supported before they are selected.
+                unsafe {
+                    $simd::vectorize(
+                        #[inline(always)]
+                        || $body,
+                    )
+                }
+            }
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Avx2 => {
+                type $simd = pipeline::Avx2;
+                // SAFETY: Backends are checked to be supported before they are selected.
+                unsafe {
+                    $simd::vectorize(
+                        #[inline(always)]
+                        || $body,
+                    )
+                }
+            }
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Sse2 => {
+                type $simd = pipeline::Sse2;
+                // SAFETY: Backends are checked to be supported before they are selected.
+                unsafe {
+                    $simd::vectorize(
+                        #[inline(always)]
+                        || $body,
+                    )
+                }
+            }
+            #[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+            Backend::Neon => {
+                type $simd = pipeline::Neon;
+                $body
+            }
+            #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+            Backend::Wasm128 => {
+                type $simd = pipeline::Wasm128;
+                $body
+            }
+            _ => {
+                type $simd = pipeline::Scalar;
+                $body
+            }
+        }
+    };
+}
+
 trait FlipCoords {
     fn winding(value: f32) -> f32;
     fn row(y: usize, height: usize) -> usize;
@@ -207,7 +320,7 @@ impl Rasterizer {
         if y_end >= self.height as i32 {
             let clip_x = p1.x + dxdy * (self.height as f32 - p1.y);
             x_end = floor(clip_x);
-            x_offset_end = clip_x - x as f32;
+            x_offset_end = clip_x - x_end as f32;
 
             y_end = self.height as i32 - 1;
             y_offset_end = 1.0;
@@ -347,34 +460,83 @@ impl Rasterizer {
         self.bitmasks[offset + bitmask_index_max] |= mask;
     }
 
-    pub fn composite(&mut self, color: Color, data: &mut [u32], stride: usize) {
-        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-        {
-            #[cfg(target_feature = "avx2")]
-            return self.composite_inner::<pipeline::Avx2>(color, data, stride);
+    /// Multiplies each 8-bit value of `mask` by the coverage of the corresponding pixel.
+    pub fn intersect_mask(&mut self, fill_rule: FillRule, mask: &mut [u8], stride: usize) {
+        for y in 0..self.height {
+            let coverage_start = y * self.width;
+            let coverage_end = coverage_start + self.width;
+            let coverage_row = &mut self.coverage[coverage_start..coverage_end];
 
-            #[cfg(all(not(target_feature = "avx2"), target_feature = "sse2"))]
-            return self.composite_inner::<pipeline::Sse2>(color, data, stride);
+            let mask_start = y * stride;
+            let mask_end = mask_start + self.width;
+            let mask_row = &mut mask[mask_start..mask_end];
 
-            #[cfg(not(any(target_feature = "avx2", target_feature = "sse2")))]
-            return self.composite_inner::<pipeline::Scalar>(color, data, stride);
-        }
+            let mut accum = 0.0;
+            for (delta, value) in coverage_row.iter_mut().zip(mask_row.iter_mut()) {
+                accum += mem::replace(delta, 0.0);
 
-        #[cfg(target_arch = "aarch64")]
-        {
-            #[cfg(target_feature = "neon")]
-            return self.composite_inner::<pipeline::Neon>(color, data, stride);
+                let winding = match fill_rule {
+                    FillRule::NonZero => accum,
+                    FillRule::EvenOdd => accum - 2.0 * (0.5 * accum).round_ties_even(),
+                };
+                *value = (*value as f32 * winding.abs().min(1.0) + 0.5) as u8;
+            }
+
+            let bitmasks_start = y * self.bitmasks_width;
+            let bitmasks_end = bitmasks_start + self.bitmasks_width;
+            self.bitmasks[bitmasks_start..bitmasks_end].fill(0);
         }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn composite(
+        &mut self,
+        shader: &Shader,
+        blend: Blend,
+        fill_rule: FillRule,
+        clip: Option<ClipMask>,
+        options: Options,
+        pixels: &mut Pixels,
+        start: usize,
+        stride: usize,
+    ) {
+        with_format!(mut pixels, start, |F, data| {
+            self.composite_format::<F>(shader, blend, fill_rule, clip, options, data, stride)
+        })
+    }
 
-        #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
-        self.composite_inner::<pipeline::Scalar>(color, data, stride)
+    #[allow(clippy::too_many_arguments)]
+    fn composite_format<F: Format>(
+        &mut self,
+        shader: &Shader,
+        blend: Blend,
+        fill_rule: FillRule,
+        clip: Option<ClipMask>,
+        options: Options,
+        data: &mut [F::Pixel],
+        stride: usize,
+    ) {
+        with_backend!(options.backend, |S| {
+            self.composite_inner::<S, F>(shader, blend, fill_rule, clip, options, data, stride)
+        })
     }
 
-    fn composite_inner<P: Pipeline>(&mut self, color: Color, data: &mut [u32], stride: usize) {
-        let mut pipeline = P::build(color);
+    #[allow(clippy::too_many_arguments)]
+    #[inline(always)]
+    fn composite_inner<S: Simd, F: Format>(
+        &mut self,
+        shader: &Shader,
+        blend: Blend,
+        fill_rule: FillRule,
+        clip: Option<ClipMask>,
+        options: Options,
+        data: &mut [F::Pixel],
+        stride: usize,
+    ) {
+        let mut pipeline = Pipeline::<S>::new(shader, blend, fill_rule, clip, options);
 
         for y in 0..self.height {
-            pipeline.reset();
+            pipeline.reset(y);
 
             let coverage_start = y * self.width;
             let coverage_end = coverage_start + self.width;
@@ -414,7 +576,7 @@ impl Rasterizer {
 
                 // Composite an interior span (or skip an empty span).
                 if next_x > x {
-                    pipeline.fill(&mut pixels_row[x..next_x]);
+                    pipeline.fill::<F>(x, &mut pixels_row[x..next_x]);
                 }
 
                 x = next_x;
@@ -444,7 +606,11 @@ impl Rasterizer {
 
                 // Composite an edge span.
                 if next_x > x {
-                    pipeline.fill_edge(&mut pixels_row[x..next_x], &mut coverage_row[x..next_x]);
+                    pipeline.fill_edge::<F>(
+                        x,
+                        &mut pixels_row[x..next_x],
+                        &mut coverage_row[x..next_x],
+                    );
                 }
 
                 x = next_x;
@@ -455,3 +621,73 @@ impl Rasterizer {
         }
     }
 }
+
+/// Composites a shader over a fully covered `width` by `height` rectangle, without rasterizing.
+#[allow(clippy::too_many_arguments)]
+pub fn fill_rect(
+    shader: &Shader,
+    blend: Blend,
+    clip: Option<ClipMask>,
+    options: Options,
+    pixels: &mut Pixels,
+    start: usize,
+    stride: usize,
+    width: usize,
+    height: usize,
+) {
+    with_format!(mut pixels, start, |F, data| {
+        fill_rect_format::<F>(shader, blend, clip, options, data, stride, width, height)
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn fill_rect_format<F: Format>(
+    shader: &Shader,
+    blend: Blend,
+    clip: Option<ClipMask>,
+    options: Options,
+    data: &mut [F::Pixel],
+    stride: usize,
+    width: usize,
+    height: usize,
+) {
+    with_backend!(options.backend, |S| {
+        fill_rect_inner::<S, F>(shader, blend, clip, options, data, stride, width, height)
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+#[inline(always)]
+fn fill_rect_inner<S: Simd, F: Format>(
+    shader: &Shader,
+    blend: Blend,
+    clip: Option<ClipMask>,
+    options: Options,
+    data: &mut [F::Pixel],
+    stride: usize,
+    width: usize,
+    height: usize,
+) {
+    let mut pipeline = Pipeline::<S>::new(shader, blend, FillRule::NonZero, clip, options);
+
+    for y in 0..height {
+        pipeline.reset(y);
+
+        let pixels_start = y * stride;
+        let pixels_end = pixels_start + width;
+        pipeline.fill_covered::<F>(0, &mut data[pixels_start..pixels_end]);
+    }
+}
+
+/// Converts pixels to native-endian `0xAARRGGBB` values with straight alpha.
+pub fn to_straight_alpha(backend: Backend, pixels: &Pixels, dst: &mut [u32]) {
+    with_format!(pixels, 0, |F, data| {
+        to_straight_alpha_format::<F>(backend, data, dst)
+    })
+}
+
+fn to_straight_alpha_format<F: Format>(backend: Backend, src: &[F::Pixel], dst: &mut [u32]) {
+    with_backend!(backend, |S| pipeline::convert::<S, F, Argb32<true>>(
+        src, dst
+    ))
+}
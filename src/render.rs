@@ -1,12 +1,24 @@
 use crate::bbox::{self, Bbox};
-use crate::color::Color;
+use crate::blend::{Blend, CompositeOp};
+use crate::blur;
+use crate::color::{Color, PremulColor};
 use crate::flatten::{self, Line};
 use crate::geom::{Affine, Point};
-use crate::path::Path;
-use crate::raster::Rasterizer;
+use crate::image::{Filter, Image};
+use crate::paint::{Extend, Paint};
+use crate::path::{FillRule, Path};
+use crate::pipeline::{self, Backend, ClipMask, Options, Shader, Srgb};
+use crate::pixels::Pixels;
+use crate::raster::{self, Rasterizer};
+use crate::stroke::StrokeStyle;
 use crate::text::{Font, Glyph, TextLayout};
 
 pub struct Renderer {
+    backend: Backend,
+    srgb: Option<Srgb>,
+    text_coverage: Option<Box<[f32]>>,
+    dither: bool,
+
     lines: Vec<Line>,
     rasterizer: Rasterizer,
 }
@@ -14,16 +26,86 @@ pub struct Renderer {
 impl Renderer {
     pub fn new() -> Renderer {
         Renderer {
+            backend: Backend::detect(),
+            srgb: None,
+            text_coverage: None,
+            dither: false,
+
             lines: Vec::new(),
             rasterizer: Rasterizer::new(),
         }
     }
 
+    /// Returns the implementation used for compositing, which is the fastest one supported by the
+    /// CPU unless overridden with `set_backend`.
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Forces compositing to use a particular implementation, for testing and benchmarking.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the backend isn't supported, which can be checked with `Backend::is_supported`.
+    pub fn set_backend(&mut self, backend: Backend) {
+        assert!(backend.is_supported(), "unsupported backend {:?}", backend);
+        self.backend = backend;
+    }
+
+    /// Sets whether colors are blended in linear light, decoding sRGB values before blending and
+    /// encoding them again afterwards. This is slower, but gives anti-aliased edges and
+    /// translucent colors the weight they would have physically. Off by default.
+    pub fn set_linear_blending(&mut self, linear: bool) {
+        if linear {
+            self.srgb.get_or_insert_with(Srgb::new);
+        } else {
+            self.srgb = None;
+        }
+    }
+
+    /// Sets whether colors are dithered with an ordered pattern when stored to formats with 8 bits
+    /// per channel or fewer, which hides banding in gradients. Off by default.
+    pub fn set_dithering(&mut self, dither: bool) {
+        self.dither = dither;
+    }
+
+    /// Sets the gamma applied to the coverage of text, which maps coverage `c` to `c^(1 / gamma)`.
+    /// Values above 1 make text heavier, which offsets the thinning of dark text on light
+    /// backgrounds in linear blending. A gamma of 1 leaves coverage unchanged, which is the default.
+    pub fn set_text_gamma(&mut self, gamma: f32) {
+        self.text_coverage = if gamma != 1.0 && gamma > 0.0 {
+            Some(pipeline::coverage_table(gamma))
+        } else {
+            None
+        };
+    }
+
+    /// Attaches to `width` by `height` pixels, in the format of `PremulColor`.
     pub fn attach<'a>(
         &'a mut self,
         data: &'a mut [u32],
         width: usize,
         height: usize,
+    ) -> RenderTarget<'a> {
+        self.attach_pixels(Pixels::Argb32(data), width, height)
+    }
+
+    /// Like `attach`, but for typed premultiplied colors.
+    pub fn attach_colors<'a>(
+        &'a mut self,
+        data: &'a mut [PremulColor],
+        width: usize,
+        height: usize,
+    ) -> RenderTarget<'a> {
+        self.attach(PremulColor::as_argb_mut(data), width, height)
+    }
+
+    /// Like `attach`, but for pixels in any supported format.
+    pub fn attach_pixels<'a>(
+        &'a mut self,
+        data: Pixels<'a>,
+        width: usize,
+        height: usize,
     ) -> RenderTarget<'a> {
         assert!(data.len() == width * height);
 
@@ -31,7 +113,24 @@ impl Renderer {
             data,
             width,
             height,
+            data_bbox: Bbox {
+                x0: 0,
+                y0: 0,
+                x1: width as i32,
+                y1: height as i32,
+            },
             transform: Affine::id(),
+            clip_rect: Bbox {
+                x0: 0,
+                y0: 0,
+                x1: width as i32,
+                y1: height as i32,
+            },
+            clip: None,
+            backend: self.backend,
+            srgb: self.srgb.as_ref(),
+            text_coverage: self.text_coverage.as_deref(),
+            dither: self.dither,
 
             lines: &mut self.lines,
             rasterizer: &mut self.rasterizer,
@@ -46,10 +145,18 @@ impl Default for Renderer {
 }
 
 pub struct RenderTarget<'a> {
-    data: &'a mut [u32],
+    data: Pixels<'a>,
     width: usize,
     height: usize,
+    /// The area of the target stored in `data`, which is all of it except inside of layers.
+    data_bbox: Bbox,
     transform: Affine,
+    clip_rect: Bbox,
+    clip: Option<Clip>,
+    backend: Backend,
+    srgb: Option<&'a Srgb>,
+    text_coverage: Option<&'a [f32]>,
+    dither: bool,
 
     lines: &'a mut Vec<Line>,
     rasterizer: &'a mut Rasterizer,
@@ -78,77 +185,522 @@ impl<'a> RenderTarget<'a> {
         result
     }
 
-    pub fn clear(&mut self, color: Color) {
-        for pixel in self.data.iter_mut() {
-            *pixel = color.into();
-        }
+    /// Restricts drawing within `f` to a rectangle of pixels, intersected with any enclosing clip.
+    /// The rectangle is in the target's pixel coordinates and is not affected by the current
+    /// transform.
+    pub fn with_clip_rect<F, R>(&mut self, rect: Bbox, f: F) -> R
+    where
+        F: FnOnce(&mut RenderTarget) -> R,
+    {
+        let saved = self.clip_rect;
+        self.clip_rect = saved.intersect(rect);
+
+        let result = f(self);
+
+        self.clip_rect = saved;
+
+        result
     }
 
-    pub fn fill_path(&mut self, path: &Path, transform: Affine, color: Color) {
+    /// Restricts drawing within `f` to the inside of a path, which is transformed and filled as in
+    /// `fill_path`. The clip is intersected with any enclosing clip, and its edges are
+    /// anti-aliased.
+    pub fn with_clip<F, R>(
+        &mut self,
+        path: &Path,
+        transform: Affine,
+        fill_rule: FillRule,
+        f: F,
+    ) -> R
+    where
+        F: FnOnce(&mut RenderTarget) -> R,
+    {
         let transform = self.transform * transform;
 
-        let clip = Bbox {
-            x0: 0,
-            y0: 0,
-            x1: self.width as i32,
-            y1: self.height as i32,
+        let mut bbox = bbox::fill(path, transform, self.clip_bbox());
+        if bbox.is_empty() {
+            bbox = Bbox {
+                x0: 0,
+                y0: 0,
+                x1: 0,
+                y1: 0,
+            };
+        }
+
+        let width = (bbox.x1 - bbox.x0) as usize;
+        let height = (bbox.y1 - bbox.y0) as usize;
+        let mut mask = vec![255; width * height];
+
+        if !bbox.is_empty() {
+            // Start from the enclosing clip, so that the new clip is the intersection of the two.
+            if let Some(ref clip) = self.clip {
+                let enclosing = clip.mask_at(bbox);
+                for (y, row) in mask.chunks_exact_mut(width).enumerate() {
+                    let start = y * enclosing.stride;
+                    row.copy_from_slice(&enclosing.data[start..start + width]);
+                }
+            }
+
+            self.rasterizer.set_size(width, height);
+
+            let offset = Affine::translate(-bbox.x0 as f32, -bbox.y0 as f32);
+            flatten::fill(path, offset * transform, self.lines);
+
+            self.rasterizer.rasterize(self.lines);
+            self.lines.clear();
+
+            self.rasterizer.intersect_mask(fill_rule, &mut mask, width);
+        }
+
+        let saved = self.clip.replace(Clip { bbox, mask });
+
+        let result = f(self);
+
+        self.clip = saved;
+
+        result
+    }
+
+    /// Restricts drawing within `f` by a mask, which is drawn by `mask` using the usual drawing
+    /// operations. The coverage of everything drawn within `f` is multiplied by the mask's alpha
+    /// or luminance, according to `mask_type`, and by any enclosing clip.
+    pub fn with_mask<M, F, R>(&mut self, mask_type: MaskType, mask: M, f: F) -> R
+    where
+        M: FnOnce(&mut RenderTarget),
+        F: FnOnce(&mut RenderTarget) -> R,
+    {
+        let mut bbox = self.clip_bbox();
+        if bbox.is_empty() {
+            bbox.x1 = bbox.x0;
+            bbox.y1 = bbox.y0;
+        }
+
+        let width = (bbox.x1 - bbox.x0) as usize;
+        let height = (bbox.y1 - bbox.y0) as usize;
+
+        // An alpha mask is drawn straight into 8-bit coverage values, while a luminance mask needs
+        // its color channels.
+        let mut values = match mask_type {
+            MaskType::Alpha => {
+                let mut values = vec![0; width * height];
+                mask(&mut self.offscreen(Pixels::A8(&mut values), bbox));
+                values
+            }
+            MaskType::Luminance => {
+                let mut mask_data = vec![0; width * height];
+                mask(&mut self.offscreen(Pixels::Argb32(&mut mask_data), bbox));
+                mask_data.iter().map(|&pixel| luminance(pixel)).collect()
+            }
+        };
+
+        if let Some(ref clip) = self.clip {
+            clip.apply(bbox, &mut values, width);
+        }
+
+        let saved = self.clip.replace(Clip { bbox, mask: values });
+
+        let result = f(self);
+
+        self.clip = saved;
+
+        result
+    }
+
+    /// Draws the contents of `f` into a separate layer, which is then composited onto the target
+    /// in a single step with the given opacity and blend mode. The layer covers the area that can
+    /// currently be drawn to, further restricted to `clip` if given.
+    pub fn with_layer<F, R>(
+        &mut self,
+        opacity: f32,
+        blend: impl Into<Blend>,
+        clip: Option<Bbox>,
+        f: F,
+    ) -> R
+    where
+        F: FnOnce(&mut RenderTarget) -> R,
+    {
+        let mut bbox = self.clip_bbox();
+        if let Some(clip) = clip {
+            bbox = bbox.intersect(clip);
+        }
+        if bbox.is_empty() {
+            bbox.x1 = bbox.x0;
+            bbox.y1 = bbox.y0;
+        }
+
+        let width = (bbox.x1 - bbox.x0) as usize;
+        let height = (bbox.y1 - bbox.y0) as usize;
+        let mut layer = Layer::new(&self.data, width * height);
+
+        // The layer is drawn without the clip mask, which is applied when compositing it instead.
+        let result = f(&mut self.offscreen(layer.pixels(), bbox));
+
+        if bbox.is_empty() {
+            return result;
+        }
+
+        let shader = match layer.shader(0, width, height, width, opacity) {
+            Some(shader) => shader,
+            None => return result,
+        };
+
+        let (data_start, stride) = self.data_start(bbox);
+        raster::fill_rect(
+            &shader,
+            blend.into(),
+            self.clip.as_ref().map(|clip| clip.mask_at(bbox)),
+            self.options(),
+            &mut self.data,
+            data_start,
+            stride,
+            width,
+            height,
+        );
+
+        result
+    }
+
+    /// Draws the contents of `f` into a separate layer, which is blurred and then composited onto
+    /// the target. The blur approximates a Gaussian blur with a standard deviation of half of
+    /// `blur_radius`, in pixels.
+    pub fn with_blur<F, R>(&mut self, blur_radius: f32, f: F) -> R
+    where
+        F: FnOnce(&mut RenderTarget) -> R,
+    {
+        let sigma = 0.5 * blur_radius;
+        let extent = blur::extent(sigma);
+        if extent == 0 {
+            return f(self);
+        }
+
+        // Draw everything that could be blurred into the visible area.
+        let clip = self.clip_bbox();
+        let mut bbox = clip;
+        if bbox.is_empty() {
+            bbox.x1 = bbox.x0;
+            bbox.y1 = bbox.y0;
+        } else {
+            bbox = dilate(bbox, extent);
+        }
+
+        let width = (bbox.x1 - bbox.x0) as usize;
+        let height = (bbox.y1 - bbox.y0) as usize;
+        let mut layer = Layer::new(&self.data, width * height);
+
+        let result = f(&mut self.offscreen(layer.pixels(), bbox));
+
+        if clip.is_empty() {
+            return result;
+        }
+
+        layer.blur(width, height, sigma);
+
+        let x = (clip.x0 - bbox.x0) as usize;
+        let y = (clip.y0 - bbox.y0) as usize;
+        let clip_width = (clip.x1 - clip.x0) as usize;
+        let clip_height = (clip.y1 - clip.y0) as usize;
+        let shader = match layer.shader(y * width + x, clip_width, clip_height, width, 1.0) {
+            Some(shader) => shader,
+            None => return result,
+        };
+
+        let (data_start, stride) = self.data_start(clip);
+        raster::fill_rect(
+            &shader,
+            Blend::default(),
+            self.clip.as_ref().map(|mask| mask.mask_at(clip)),
+            self.options(),
+            &mut self.data,
+            data_start,
+            stride,
+            clip_width,
+            clip_height,
+        );
+
+        result
+    }
+
+    /// Draws a blurred shadow of a path, filled with the current transform and the nonzero fill
+    /// rule. `offset` is in pixels and is not affected by the transform, and the blur is the same
+    /// as in `with_blur`.
+    pub fn drop_shadow(&mut self, path: &Path, offset: Point, blur_radius: f32, color: Color) {
+        let sigma = 0.5 * blur_radius;
+        let extent = blur::extent(sigma);
+        let transform = Affine::translate(offset.x, offset.y) * self.transform;
+
+        let clip = self.clip_bbox();
+        if clip.is_empty() {
+            return;
+        }
+
+        // The shadow can spread into the visible area from a path outside of it.
+        let bbox = bbox::fill(path, transform, dilate(clip, extent));
+        if bbox.is_empty() {
+            return;
+        }
+        let bbox = dilate(bbox, extent);
+
+        let width = (bbox.x1 - bbox.x0) as usize;
+        let height = (bbox.y1 - bbox.y0) as usize;
+        self.rasterizer.set_size(width, height);
+
+        let offset = Affine::translate(-bbox.x0 as f32, -bbox.y0 as f32);
+        flatten::fill(path, offset * transform, self.lines);
+
+        self.rasterizer.rasterize(self.lines);
+        self.lines.clear();
+
+        let mut mask = vec![255; width * height];
+        self.rasterizer.intersect_mask(FillRule::NonZero, &mut mask, width);
+        blur::blur_mask(&mut mask, width, height, sigma);
+
+        let visible = bbox.intersect(clip);
+        if visible.is_empty() {
+            return;
+        }
+
+        let x = (visible.x0 - bbox.x0) as usize;
+        let y = (visible.y0 - bbox.y0) as usize;
+        let mask = &mut mask[y * width + x..];
+        if let Some(ref clip) = self.clip {
+            clip.apply(visible, mask, width);
+        }
+
+        let shader = match Shader::new(&Paint::Color(color), Affine::id()) {
+            Some(shader) => shader,
+            None => return,
         };
-        let bbox = bbox::fill(path, transform, clip);
+
+        let (data_start, stride) = self.data_start(visible);
+        raster::fill_rect(
+            &shader,
+            Blend::default(),
+            Some(ClipMask {
+                data: mask,
+                stride: width,
+            }),
+            self.options(),
+            &mut self.data,
+            data_start,
+            stride,
+            (visible.x1 - visible.x0) as usize,
+            (visible.y1 - visible.y0) as usize,
+        );
+    }
+
+    /// Returns a target which draws the area `bbox` into `data`, with the transform and settings
+    /// of this target but without its clip.
+    fn offscreen<'b>(&'b mut self, data: Pixels<'b>, bbox: Bbox) -> RenderTarget<'b> {
+        RenderTarget {
+            data,
+            width: self.width,
+            height: self.height,
+            data_bbox: bbox,
+            transform: self.transform,
+            clip_rect: bbox,
+            clip: None,
+
+            backend: self.backend,
+            srgb: self.srgb,
+            text_coverage: self.text_coverage,
+            dither: self.dither,
+
+            lines: self.lines,
+            rasterizer: self.rasterizer,
+        }
+    }
+
+    /// Returns the offset in `data` of the top left corner of `bbox`, along with the stride of
+    /// `data`.
+    fn data_start(&self, bbox: Bbox) -> (usize, usize) {
+        let stride = (self.data_bbox.x1 - self.data_bbox.x0) as usize;
+        let x = (bbox.x0 - self.data_bbox.x0) as usize;
+        let y = (bbox.y0 - self.data_bbox.y0) as usize;
+        (y * stride + x, stride)
+    }
+
+    /// Returns the pipeline options for compositing anything other than text.
+    fn options(&self) -> Options<'a> {
+        Options {
+            backend: self.backend,
+            linear: self.srgb,
+            coverage: None,
+            dither: self.dither,
+        }
+    }
+
+    /// Returns the bounds of the area that can currently be drawn to.
+    fn clip_bbox(&self) -> Bbox {
+        match self.clip {
+            Some(ref clip) => self.clip_rect.intersect(clip.bbox),
+            None => self.clip_rect,
+        }
+    }
+
+    /// Writes every pixel of the target to `dst` with straight alpha, in the format of `Color`,
+    /// for export to PNG files and other libraries which expect unpremultiplied colors.
+    pub fn to_straight_alpha(&self, dst: &mut [u32]) {
+        assert!(dst.len() == self.data.len());
+
+        raster::to_straight_alpha(self.backend, &self.data, dst);
+    }
+
+    /// Sets every pixel of the target to `color`, which may be a `Color` or a `PremulColor`.
+    pub fn clear(&mut self, color: impl Into<PremulColor>) {
+        let shader = Shader::premultiplied(color.into());
+
+        let (_, stride) = self.data_start(self.data_bbox);
+        raster::fill_rect(
+            &shader,
+            CompositeOp::Src.into(),
+            None,
+            Options {
+                backend: self.backend,
+                ..Options::default()
+            },
+            &mut self.data,
+            0,
+            stride,
+            stride,
+            (self.data_bbox.y1 - self.data_bbox.y0) as usize,
+        );
+    }
+
+    pub fn fill_path<'p>(
+        &mut self,
+        path: &Path,
+        transform: Affine,
+        fill_rule: FillRule,
+        paint: impl Into<Paint<'p>>,
+        blend: impl Into<Blend>,
+    ) {
+        let transform = self.transform * transform;
+        self.fill_path_inner(
+            path,
+            transform,
+            transform,
+            fill_rule,
+            &paint.into(),
+            blend.into(),
+            self.options(),
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn fill_path_inner(
+        &mut self,
+        path: &Path,
+        transform: Affine,
+        paint_transform: Affine,
+        fill_rule: FillRule,
+        paint: &Paint,
+        blend: Blend,
+        options: Options,
+    ) {
+        self.fill_path_with(path, transform, fill_rule, blend, options, |offset| {
+            Shader::new(paint, offset * paint_transform)
+        });
+    }
+
+    /// Fills a path with the shader returned by `shader`, which is given the transformation from
+    /// the target's coordinates to the coordinates of the area being composited.
+    fn fill_path_with<'s, F>(
+        &mut self,
+        path: &Path,
+        transform: Affine,
+        fill_rule: FillRule,
+        blend: Blend,
+        options: Options,
+        shader: F,
+    ) where
+        F: FnOnce(Affine) -> Option<Shader<'s>>,
+    {
+        let bbox = bbox::fill(path, transform, self.clip_bbox());
 
         if bbox.is_empty() {
             return;
         }
 
+        let offset = Affine::translate(-bbox.x0 as f32, -bbox.y0 as f32);
+        let shader = match shader(offset) {
+            Some(shader) => shader,
+            None => return,
+        };
+
         let path_width = (bbox.x1 - bbox.x0) as usize;
         let path_height = (bbox.y1 - bbox.y0) as usize;
         self.rasterizer.set_size(path_width, path_height);
 
-        let offset = Affine::translate(-bbox.x0 as f32, -bbox.y0 as f32);
         flatten::fill(path, offset * transform, &mut self.lines);
 
         self.rasterizer.rasterize(&self.lines);
         self.lines.clear();
 
-        let data_start = bbox.y0 as usize * self.width + bbox.x0 as usize;
-        self.rasterizer.composite(color, &mut self.data[data_start..], self.width);
+        let (data_start, stride) = self.data_start(bbox);
+        self.rasterizer.composite(
+            &shader,
+            blend,
+            fill_rule,
+            self.clip.as_ref().map(|clip| clip.mask_at(bbox)),
+            options,
+            &mut self.data,
+            data_start,
+            stride,
+        );
     }
 
-    pub fn stroke_path(&mut self, path: &Path, width: f32, transform: Affine, color: Color) {
+    pub fn stroke_path<'p>(
+        &mut self,
+        path: &Path,
+        style: &StrokeStyle,
+        transform: Affine,
+        paint: impl Into<Paint<'p>>,
+        blend: impl Into<Blend>,
+    ) {
         let transform = self.transform * transform;
 
-        let clip = Bbox {
-            x0: 0,
-            y0: 0,
-            x1: self.width as i32,
-            y1: self.height as i32,
-        };
-        let bbox = bbox::stroke(path, width, transform, clip);
+        let bbox = bbox::stroke(path, style, transform, self.clip_bbox());
 
         if bbox.is_empty() {
             return;
         }
 
+        let offset = Affine::translate(-bbox.x0 as f32, -bbox.y0 as f32);
+        let shader = match Shader::new(&paint.into(), offset * transform) {
+            Some(shader) => shader,
+            None => return,
+        };
+
         let path_width = (bbox.x1 - bbox.x0) as usize;
         let path_height = (bbox.y1 - bbox.y0) as usize;
         self.rasterizer.set_size(path_width, path_height);
 
-        let offset = Affine::translate(-bbox.x0 as f32, -bbox.y0 as f32);
-        flatten::stroke(path, width, offset * transform, &mut self.lines);
+        flatten::stroke(path, style, offset * transform, &mut self.lines);
 
         self.rasterizer.rasterize(&self.lines);
         self.lines.clear();
 
-        let data_start = bbox.y0 as usize * self.width + bbox.x0 as usize;
-        self.rasterizer.composite(color, &mut self.data[data_start..], self.width);
+        let (data_start, stride) = self.data_start(bbox);
+        self.rasterizer.composite(
+            &shader,
+            blend.into(),
+            FillRule::NonZero,
+            self.clip.as_ref().map(|clip| clip.mask_at(bbox)),
+            self.options(),
+            &mut self.data,
+            data_start,
+            stride,
+        );
     }
 
-    pub fn fill_glyphs(
+    pub fn fill_glyphs<'p>(
         &mut self,
         glyphs: &[Glyph],
         font: &Font,
         size: f32,
         transform: Affine,
-        color: Color,
+        paint: impl Into<Paint<'p>>,
+        blend: impl Into<Blend>,
     ) {
         use rustybuzz::ttf_parser::{GlyphId, OutlineBuilder};
 
@@ -186,7 +738,14 @@ impl<'a> RenderTarget<'a> {
             }
         }
 
+        let paint = paint.into();
+        let blend = blend.into();
+        let transform = self.transform * transform;
         let scale = size / font.face.units_per_em() as f32;
+        let options = Options {
+            coverage: self.text_coverage,
+            ..self.options()
+        };
 
         for glyph in glyphs {
             let mut builder = Builder {
@@ -195,21 +754,260 @@ impl<'a> RenderTarget<'a> {
             };
             font.face.outline_glyph(GlyphId(glyph.id), &mut builder);
 
-            let transform = transform * Affine::translate(glyph.x, glyph.y) * Affine::scale(scale);
-
-            self.fill_path(&builder.path, transform, color);
+            let glyph_transform =
+                transform * Affine::translate(glyph.x, glyph.y) * Affine::scale(scale);
+
+            self.fill_path_inner(
+                &builder.path,
+                glyph_transform,
+                transform,
+                FillRule::NonZero,
+                &paint,
+                blend,
+                options,
+            );
         }
     }
 
-    pub fn fill_text(
+    pub fn fill_text<'p>(
         &mut self,
         text: &str,
         font: &Font,
         size: f32,
         transform: Affine,
-        color: Color,
+        paint: impl Into<Paint<'p>>,
+        blend: impl Into<Blend>,
     ) {
         let layout = TextLayout::new(text, font, size);
-        self.fill_glyphs(layout.glyphs(), font, size, transform, color);
+        self.fill_glyphs(layout.glyphs(), font, size, transform, paint, blend);
+    }
+
+    pub fn draw_image(
+        &mut self,
+        image: Image,
+        src_rect: Bbox,
+        transform: Affine,
+        opacity: f32,
+        filter: Filter,
+        blend: impl Into<Blend>,
+    ) {
+        // Restrict drawing to the source rectangle, so that filtering does not pick up the pixels
+        // around it.
+        let x0 = src_rect.x0.max(0) as usize;
+        let y0 = src_rect.y0.max(0) as usize;
+        let x1 = (src_rect.x1.max(0) as usize).min(image.width());
+        let y1 = (src_rect.y1.max(0) as usize).min(image.height());
+        if x1 <= x0 || y1 <= y0 {
+            return;
+        }
+
+        let src_width = x1 - x0;
+        let src_height = y1 - y0;
+        let src_start = y0 * image.stride() + x0;
+        let image = Image::new(
+            &image.data()[src_start..],
+            src_width,
+            src_height,
+            image.stride(),
+        );
+
+        // Parts of the source rectangle outside the image are cut off, so the part that remains
+        // is drawn where it would have been.
+        let cut = Affine::translate(
+            x0 as f32 - src_rect.x0 as f32,
+            y0 as f32 - src_rect.y0 as f32,
+        );
+        let transform = self.transform * transform * cut;
+        let blend = blend.into();
+
+        let [a, b, c, d, e, f] = transform.coeffs();
+        let is_integer = |value: f32| value == value.round() && value.abs() <= (1 << 24) as f32;
+        let axis_aligned = b == 0.0
+            && d == 0.0
+            && a >= 1.0
+            && e >= 1.0
+            && is_integer(a)
+            && is_integer(c)
+            && is_integer(e)
+            && is_integer(f);
+
+        // An image scaled by whole numbers covers whole pixels, so it can be drawn as a rectangle
+        // without computing coverage. Without scaling, every pixel center also lands on a pixel
+        // center of the image, where bilinear filtering has no effect.
+        if axis_aligned {
+            let filter = if a == 1.0 && e == 1.0 && filter == Filter::Bilinear {
+                Filter::Nearest
+            } else {
+                filter
+            };
+
+            let clip = self.clip_bbox();
+            let clip_x = |x: f32| x.max(clip.x0 as f32).min(clip.x1 as f32) as i32;
+            let clip_y = |y: f32| y.max(clip.y0 as f32).min(clip.y1 as f32) as i32;
+            let bbox = Bbox {
+                x0: clip_x(c),
+                y0: clip_y(f),
+                x1: clip_x(c + a * src_width as f32),
+                y1: clip_y(f + e * src_height as f32),
+            };
+
+            if bbox.is_empty() {
+                return;
+            }
+
+            let offset = Affine::translate(-bbox.x0 as f32, -bbox.y0 as f32);
+            let shader =
+                match Shader::image(image, offset * transform, Extend::Pad, filter, opacity) {
+                    Some(shader) => shader,
+                    None => return,
+                };
+
+            let (data_start, stride) = self.data_start(bbox);
+            raster::fill_rect(
+                &shader,
+                blend,
+                self.clip.as_ref().map(|clip| clip.mask_at(bbox)),
+                self.options(),
+                &mut self.data,
+                data_start,
+                stride,
+                (bbox.x1 - bbox.x0) as usize,
+                (bbox.y1 - bbox.y0) as usize,
+            );
+        } else {
+            let mut path = Path::new();
+            path.move_to(Point::new(0.0, 0.0))
+                .line_to(Point::new(src_width as f32, 0.0))
+                .line_to(Point::new(src_width as f32, src_height as f32))
+                .line_to(Point::new(0.0, src_height as f32))
+                .close();
+
+            self.fill_path_with(
+                &path,
+                transform,
+                FillRule::NonZero,
+                blend,
+                self.options(),
+                |offset| Shader::image(image, offset * transform, Extend::Pad, filter, opacity),
+            );
+        }
+    }
+}
+
+/// Grows a rectangle by `amount` pixels on each side.
+fn dilate(bbox: Bbox, amount: usize) -> Bbox {
+    let amount = amount as i32;
+    Bbox {
+        x0: bbox.x0 - amount,
+        y0: bbox.y0 - amount,
+        x1: bbox.x1 + amount,
+        y1: bbox.y1 + amount,
+    }
+}
+
+/// Determines which channel of a mask controls the coverage of what is drawn through it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MaskType {
+    /// Uses the alpha of the mask.
+    Alpha,
+    /// Uses the luminance of the mask, premultiplied by its alpha. Luminance is computed from the
+    /// sRGB-encoded color with the Rec. 709 coefficients, as in CSS Masking.
+    Luminance,
+}
+
+/// Computes the luminance of a premultiplied pixel with the Rec. 709 coefficients, as CSS Masking
+/// specifies for luminance masks. Like in browsers, the coefficients are applied to the
+/// sRGB-encoded channels directly rather than to linear values.
+fn luminance(pixel: u32) -> u8 {
+    let r = (pixel >> 16 & 0xFF) as f32;
+    let g = (pixel >> 8 & 0xFF) as f32;
+    let b = (pixel & 0xFF) as f32;
+    (0.2126 * r + 0.7152 * g + 0.0722 * b + 0.5).min(255.0) as u8
+}
+
+/// The pixels of a layer. Layers of float targets are stored as floats as well, so that drawing
+/// through them keeps the precision and range of the target.
+enum Layer {
+    Argb32(Vec<u32>),
+    RgbaF32(Vec<f32>),
+}
+
+impl Layer {
+    /// Allocates `len` transparent pixels for drawing onto `target`.
+    fn new(target: &Pixels, len: usize) -> Layer {
+        match *target {
+            Pixels::RgbaF32(_) => Layer::RgbaF32(vec![0.0; 4 * len]),
+            _ => Layer::Argb32(vec![0; len]),
+        }
+    }
+
+    fn pixels(&mut self) -> Pixels<'_> {
+        match *self {
+            Layer::Argb32(ref mut data) => Pixels::Argb32(data),
+            Layer::RgbaF32(ref mut data) => Pixels::RgbaF32(data),
+        }
+    }
+
+    fn blur(&mut self, width: usize, height: usize, sigma: f32) {
+        match *self {
+            Layer::Argb32(ref mut data) => blur::blur_pixels(data, width, height, sigma),
+            Layer::RgbaF32(ref mut data) => blur::blur_float_pixels(data, width, height, sigma),
+        }
+    }
+
+    /// Returns a shader which draws `width` by `height` pixels of the layer, starting at pixel
+    /// `start`, with rows `stride` pixels apart, onto a rectangle of the same size.
+    fn shader(
+        &self,
+        start: usize,
+        width: usize,
+        height: usize,
+        stride: usize,
+        opacity: f32,
+    ) -> Option<Shader<'_>> {
+        match *self {
+            Layer::Argb32(ref data) => {
+                let image = Image::new(&data[start..], width, height, stride);
+                Shader::image(image, Affine::id(), Extend::Pad, Filter::Nearest, opacity)
+            }
+            Layer::RgbaF32(ref data) => {
+                Shader::layer(&data[4 * start..], width, height, stride, opacity)
+            }
+        }
+    }
+}
+
+/// An anti-aliased clip, stored as coverage values for the pixels in `bbox`. Everything outside of
+/// `bbox` is clipped out.
+struct Clip {
+    bbox: Bbox,
+    mask: Vec<u8>,
+}
+
+impl Clip {
+    /// Multiplies the 8-bit values for the pixels in `bbox`, which must lie within the clip, by
+    /// the clip's coverage.
+    fn apply(&self, bbox: Bbox, values: &mut [u8], stride: usize) {
+        let mask = self.mask_at(bbox);
+        let width = (bbox.x1 - bbox.x0) as usize;
+        for y in 0..(bbox.y1 - bbox.y0) as usize {
+            let values = &mut values[y * stride..y * stride + width];
+            let coverage = &mask.data[y * mask.stride..y * mask.stride + width];
+            for (value, &alpha) in values.iter_mut().zip(coverage) {
+                *value = ((*value as u32 * alpha as u32 + 127) / 255) as u8;
+            }
+        }
+    }
+
+    /// Returns the part of the mask starting at the top left corner of `bbox`, which must lie
+    /// within the clip.
+    fn mask_at(&self, bbox: Bbox) -> ClipMask<'_> {
+        let stride = (self.bbox.x1 - self.bbox.x0) as usize;
+        let x = (bbox.x0 - self.bbox.x0) as usize;
+        let y = (bbox.y0 - self.bbox.y0) as usize;
+        ClipMask {
+            data: &self.mask[y * stride + x..],
+            stride,
+        }
     }
 }
@@ -0,0 +1,70 @@
+/// The style used to stroke a path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrokeStyle {
+    /// The width of the stroke.
+    pub width: f32,
+    /// The shape used to join segments.
+    pub join: LineJoin,
+    /// The shape used at the ends of open subpaths.
+    pub cap: LineCap,
+    /// The maximum ratio of the miter length to the stroke width for miter joins. Joins which would
+    /// exceed it are beveled instead.
+    pub miter_limit: f32,
+    /// The dash pattern, or `None` for a solid stroke.
+    pub dash: Option<Dash>,
+}
+
+impl StrokeStyle {
+    /// Creates a solid stroke of the given width with miter joins and butt caps.
+    #[inline]
+    pub fn new(width: f32) -> StrokeStyle {
+        StrokeStyle {
+            width,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            miter_limit: 4.0,
+            dash: None,
+        }
+    }
+}
+
+/// The shape used to join two segments of a stroked path.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LineJoin {
+    /// Extends the outer edges of the two segments until they meet, falling back to a bevel join
+    /// past the miter limit.
+    Miter,
+    /// Connects the outer edges of the two segments with a circular arc.
+    Round,
+    /// Connects the outer edges of the two segments with a straight line.
+    Bevel,
+}
+
+/// The shape used at the ends of open subpaths of a stroked path.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LineCap {
+    /// Ends the stroke flush with the endpoint of the path.
+    Butt,
+    /// Extends the stroke past the endpoint of the path by half the stroke width.
+    Square,
+    /// Ends the stroke with a semicircle centered on the endpoint of the path.
+    Round,
+}
+
+/// A dash pattern for a stroked path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dash {
+    /// The lengths of alternating dashes and gaps, starting with a dash.
+    ///
+    /// If the array has an odd number of entries, it is repeated to produce an even number.
+    pub array: Vec<f32>,
+    /// The distance into the pattern at which the start of each subpath begins.
+    pub offset: f32,
+}
+
+impl Dash {
+    #[inline]
+    pub fn new(array: Vec<f32>, offset: f32) -> Dash {
+        Dash { array, offset }
+    }
+}
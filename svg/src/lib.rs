@@ -1,106 +1,519 @@
+use std::collections::HashMap;
 use std::error::Error;
 use std::path;
 
-use flicker::{Affine, Color, Path, Point, RenderTarget};
+use flicker::{
+    Affine, BlendMode, Color, CompositeOp, Dash, Extend, FillRule, Gradient, GradientKind, LineCap,
+    LineJoin, MaskType, Path, Point, RenderTarget, Stop, StrokeStyle,
+};
 
 pub enum Style {
-    Fill,
-    Stroke(f32),
+    Fill(FillRule),
+    Stroke(StrokeStyle),
 }
 
-pub struct Command {
-    pub path: Path,
-    pub style: Style,
-    pub color: Color,
+pub enum Paint {
+    Color(Color),
+    Gradient(Gradient),
+}
+
+impl<'a> From<&'a Paint> for flicker::Paint<'a> {
+    fn from(paint: &'a Paint) -> flicker::Paint<'a> {
+        match *paint {
+            Paint::Color(color) => flicker::Paint::Color(color),
+            Paint::Gradient(ref gradient) => flicker::Paint::Gradient(gradient),
+        }
+    }
+}
+
+pub enum Command {
+    Path {
+        path: Path,
+        style: Style,
+        paint: Paint,
+    },
+    /// Draws a list of commands into a layer, which is composited with the given opacity and blend
+    /// mode and through an optional mask.
+    Group {
+        opacity: f32,
+        blend: BlendMode,
+        mask: Option<Mask>,
+        commands: Vec<Command>,
+    },
+}
+
+/// A luminance mask, drawn by a list of commands and limited to the inside of `rect`.
+pub struct Mask {
+    pub rect: Path,
+    pub commands: Vec<Command>,
 }
 
 pub fn from_file<P: AsRef<path::Path>>(path: P) -> Result<Vec<Command>, Box<dyn Error>> {
-    let tree = usvg::Tree::from_file(path, &usvg::Options::default())?;
+    from_str(&usvg::load_svg_file(path.as_ref())?)
+}
+
+pub fn from_str(text: &str) -> Result<Vec<Command>, Box<dyn Error>> {
+    let (text, blend_modes) = find_blend_modes(text)?;
+
+    // Named groups are kept, so that the groups with blend modes can be found by their ids.
+    let options = usvg::Options {
+        keep_named_groups: true,
+        ..usvg::Options::default()
+    };
+    let tree = usvg::Tree::from_str(&text, &options)?;
 
     let mut commands = Vec::new();
-    build_list(&tree.root(), &mut commands);
+    build_list(
+        &tree,
+        &tree.root(),
+        Affine::id(),
+        &blend_modes,
+        &mut commands,
+    );
 
     Ok(commands)
 }
 
-fn build_list(node: &usvg::Node, commands: &mut Vec<Command>) {
-    use usvg::NodeExt;
+/// Finds the elements with a `mix-blend-mode` other than `normal`, which usvg does not parse.
+/// Elements without an id are given one, so that the blend modes can be looked up by the ids of
+/// the nodes usvg produces.
+fn find_blend_modes(text: &str) -> Result<(String, HashMap<String, BlendMode>), Box<dyn Error>> {
+    let options = roxmltree::ParsingOptions { allow_dtd: true };
+    let doc = roxmltree::Document::parse_with_options(text, options)?;
+
+    let mut blend_modes = HashMap::new();
+    let mut insertions = Vec::new();
+    for (index, node) in doc.descendants().filter(|node| node.is_element()).enumerate() {
+        let blend = match node_blend_mode(&node) {
+            Some(blend) if blend != BlendMode::Normal => blend,
+            _ => continue,
+        };
+
+        let id = match node.attribute("id") {
+            Some(id) => id.to_string(),
+            None => {
+                let id = format!("flicker-blend-{}", index);
+                // Insert the id after the tag name, which follows the `<` at the start of the
+                // element.
+                let start = node.range().start + 1;
+                let name_len = text[start..]
+                    .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+                    .unwrap_or(0);
+                insertions.push((start + name_len, format!(" id=\"{}\"", id)));
+                id
+            }
+        };
+        blend_modes.insert(id, blend);
+    }
+
+    let mut result = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (position, insertion) in insertions {
+        result.push_str(&text[copied..position]);
+        result.push_str(&insertion);
+        copied = position;
+    }
+    result.push_str(&text[copied..]);
+
+    Ok((result, blend_modes))
+}
+
+/// Reads the `mix-blend-mode` of an element, from either its attribute or its `style` attribute.
+fn node_blend_mode(node: &roxmltree::Node) -> Option<BlendMode> {
+    let declarations = node.attribute("style").into_iter().flat_map(|style| style.split(';'));
+    let value = declarations
+        .filter_map(|declaration| declaration.split_once(':'))
+        .filter(|(name, _)| name.trim() == "mix-blend-mode")
+        .map(|(_, value)| value)
+        .next_back()
+        .or_else(|| node.attribute("mix-blend-mode"))?;
+
+    let blend = match value.trim() {
+        "normal" => BlendMode::Normal,
+        "multiply" => BlendMode::Multiply,
+        "screen" => BlendMode::Screen,
+        "overlay" => BlendMode::Overlay,
+        "darken" => BlendMode::Darken,
+        "lighten" => BlendMode::Lighten,
+        "color-dodge" => BlendMode::ColorDodge,
+        "color-burn" => BlendMode::ColorBurn,
+        "hard-light" => BlendMode::HardLight,
+        "soft-light" => BlendMode::SoftLight,
+        "difference" => BlendMode::Difference,
+        "exclusion" => BlendMode::Exclusion,
+        "hue" => BlendMode::Hue,
+        "saturation" => BlendMode::Saturation,
+        "color" => BlendMode::Color,
+        "luminosity" => BlendMode::Luminosity,
+        _ => return None,
+    };
+    Some(blend)
+}
+
+fn build_list(
+    tree: &usvg::Tree,
+    node: &usvg::Node,
+    transform: Affine,
+    blend_modes: &HashMap<String, BlendMode>,
+    commands: &mut Vec<Command>,
+) {
     match *node.borrow() {
         usvg::NodeKind::Path(ref p) => {
-            let t = node.transform();
-            let transform = Affine::new([
-                t.a as f32, t.c as f32, t.e as f32, t.b as f32, t.d as f32, t.f as f32,
-            ]);
-
-            let mut path = Path::new();
-            for segment in p.data.0.iter() {
-                match *segment {
-                    usvg::PathSegment::MoveTo { x, y } => {
-                        path.move_to(transform * Point::new(x as f32, y as f32));
-                    }
-                    usvg::PathSegment::LineTo { x, y } => {
-                        path.line_to(transform * Point::new(x as f32, y as f32));
-                    }
-                    usvg::PathSegment::CurveTo {
-                        x1,
-                        y1,
-                        x2,
-                        y2,
-                        x,
-                        y,
-                    } => {
-                        path.cubic_to(
-                            transform * Point::new(x1 as f32, y1 as f32),
-                            transform * Point::new(x2 as f32, y2 as f32),
-                            transform * Point::new(x as f32, y as f32),
-                        );
-                    }
-                    usvg::PathSegment::ClosePath => {
-                        path.close();
-                    }
-                }
+            // A path with a blend mode is drawn like a group containing only the path.
+            if let Some(&blend) = blend_modes.get(&p.id) {
+                let mut path_commands = Vec::new();
+                build_path(tree, node, p, transform, &mut path_commands);
+                commands.push(Command::Group {
+                    opacity: 1.0,
+                    blend,
+                    mask: None,
+                    commands: path_commands,
+                });
+            } else {
+                build_path(tree, node, p, transform, commands);
             }
+        }
+        usvg::NodeKind::Group(ref g) => {
+            let transform = transform * convert_transform(g.transform);
+            let mask = g
+                .mask
+                .as_ref()
+                .and_then(|id| convert_mask(tree, node, id, transform, blend_modes));
+            let blend = blend_modes.get(&g.id).copied().unwrap_or(BlendMode::Normal);
 
-            if let Some(ref fill) = p.fill {
-                if let usvg::Paint::Color(color) = fill.paint {
-                    let color =
-                        Color::rgba(color.red, color.green, color.blue, fill.opacity.to_u8());
-                    commands.push(Command {
-                        path: path.clone(),
-                        style: Style::Fill,
-                        color,
-                    });
+            if g.opacity.value() < 1.0 || blend != BlendMode::Normal || mask.is_some() {
+                let mut group_commands = Vec::new();
+                for child in node.children() {
+                    build_list(tree, &child, transform, blend_modes, &mut group_commands);
                 }
-            }
 
-            if let Some(ref stroke) = p.stroke {
-                if let usvg::Paint::Color(color) = stroke.paint {
-                    let color =
-                        Color::rgba(color.red, color.green, color.blue, stroke.opacity.to_u8());
-                    commands.push(Command {
-                        path,
-                        style: Style::Stroke(stroke.width.value() as f32),
-                        color,
-                    });
+                commands.push(Command::Group {
+                    opacity: g.opacity.value() as f32,
+                    blend,
+                    mask,
+                    commands: group_commands,
+                });
+            } else {
+                for child in node.children() {
+                    build_list(tree, &child, transform, blend_modes, commands);
                 }
             }
         }
-        _ => {}
+        _ => {
+            for child in node.children() {
+                build_list(tree, &child, transform, blend_modes, commands);
+            }
+        }
     }
+}
+
+fn build_path(
+    tree: &usvg::Tree,
+    node: &usvg::Node,
+    p: &usvg::Path,
+    transform: Affine,
+    commands: &mut Vec<Command>,
+) {
+    use usvg::NodeExt;
+    let transform = transform * convert_transform(node.transform());
 
+    let mut path = Path::new();
+    for segment in p.data.0.iter() {
+        match *segment {
+            usvg::PathSegment::MoveTo { x, y } => {
+                path.move_to(transform * Point::new(x as f32, y as f32));
+            }
+            usvg::PathSegment::LineTo { x, y } => {
+                path.line_to(transform * Point::new(x as f32, y as f32));
+            }
+            usvg::PathSegment::CurveTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => {
+                path.cubic_to(
+                    transform * Point::new(x1 as f32, y1 as f32),
+                    transform * Point::new(x2 as f32, y2 as f32),
+                    transform * Point::new(x as f32, y as f32),
+                );
+            }
+            usvg::PathSegment::ClosePath => {
+                path.close();
+            }
+        }
+    }
+
+    if let Some(ref fill) = p.fill {
+        let paint = convert_paint(tree, &fill.paint, fill.opacity, &p.data, transform);
+        if let Some(paint) = paint {
+            let fill_rule = match fill.rule {
+                usvg::FillRule::NonZero => FillRule::NonZero,
+                usvg::FillRule::EvenOdd => FillRule::EvenOdd,
+            };
+            commands.push(Command::Path {
+                path: path.clone(),
+                style: Style::Fill(fill_rule),
+                paint,
+            });
+        }
+    }
+
+    if let Some(ref stroke) = p.stroke {
+        let paint = convert_paint(tree, &stroke.paint, stroke.opacity, &p.data, transform);
+        if let Some(paint) = paint {
+            let join = match stroke.linejoin {
+                usvg::LineJoin::Miter => LineJoin::Miter,
+                usvg::LineJoin::Round => LineJoin::Round,
+                usvg::LineJoin::Bevel => LineJoin::Bevel,
+            };
+            let cap = match stroke.linecap {
+                usvg::LineCap::Butt => LineCap::Butt,
+                usvg::LineCap::Square => LineCap::Square,
+                usvg::LineCap::Round => LineCap::Round,
+            };
+            let dash = stroke.dasharray.as_ref().map(|array| {
+                Dash::new(
+                    array.iter().map(|&length| length as f32).collect(),
+                    stroke.dashoffset,
+                )
+            });
+            commands.push(Command::Path {
+                path,
+                style: Style::Stroke(StrokeStyle {
+                    width: stroke.width.value() as f32,
+                    join,
+                    cap,
+                    miter_limit: stroke.miterlimit.value() as f32,
+                    dash,
+                }),
+                paint,
+            });
+        }
+    }
+}
+
+fn convert_mask(
+    tree: &usvg::Tree,
+    node: &usvg::Node,
+    id: &str,
+    transform: Affine,
+    blend_modes: &HashMap<String, BlendMode>,
+) -> Option<Mask> {
+    let mask_node = tree.defs_by_id(id)?;
+    let (units, content_units, rect) = match *mask_node.borrow() {
+        usvg::NodeKind::Mask(ref mask) => (mask.units, mask.content_units, mask.rect),
+        _ => return None,
+    };
+
+    let bbox = object_bbox(node, usvg::Transform::default());
+    let rect = if units == usvg::Units::ObjectBoundingBox {
+        rect.bbox_transform(bbox?)
+    } else {
+        rect
+    };
+    let content_transform = if content_units == usvg::Units::ObjectBoundingBox {
+        let bbox = bbox?;
+        transform
+            * Affine::new([
+                bbox.width() as f32,
+                0.0,
+                bbox.x() as f32,
+                0.0,
+                bbox.height() as f32,
+                bbox.y() as f32,
+            ])
+    } else {
+        transform
+    };
+
+    let (x0, y0) = (rect.x() as f32, rect.y() as f32);
+    let (x1, y1) = (rect.right() as f32, rect.bottom() as f32);
+    let mut path = Path::new();
+    path.move_to(transform * Point::new(x0, y0))
+        .line_to(transform * Point::new(x1, y0))
+        .line_to(transform * Point::new(x1, y1))
+        .line_to(transform * Point::new(x0, y1))
+        .close();
+
+    let mut commands = Vec::new();
+    for child in mask_node.children() {
+        build_list(tree, &child, content_transform, blend_modes, &mut commands);
+    }
+
+    Some(Mask {
+        rect: path,
+        commands,
+    })
+}
+
+/// Computes the bounding box of a node's children, excluding strokes, in the coordinate system
+/// given by `ts`.
+fn object_bbox(node: &usvg::Node, ts: usvg::Transform) -> Option<usvg::Rect> {
+    use usvg::NodeExt;
+
+    let mut bbox: Option<usvg::Rect> = None;
     for child in node.children() {
-        build_list(&child, commands);
+        let mut child_ts = ts;
+        child_ts.append(&child.transform());
+
+        let child_bbox = match *child.borrow() {
+            usvg::NodeKind::Path(ref p) => p.data.bbox_with_transform(child_ts, None),
+            usvg::NodeKind::Group(_) => object_bbox(&child, child_ts),
+            _ => None,
+        };
+
+        if let Some(child_bbox) = child_bbox {
+            bbox = Some(match bbox {
+                Some(bbox) => bbox.expand(child_bbox),
+                None => child_bbox,
+            });
+        }
     }
+
+    bbox
+}
+
+fn convert_transform(t: usvg::Transform) -> Affine {
+    Affine::new([
+        t.a as f32, t.c as f32, t.e as f32, t.b as f32, t.d as f32, t.f as f32,
+    ])
+}
+
+fn convert_paint(
+    tree: &usvg::Tree,
+    paint: &usvg::Paint,
+    opacity: usvg::Opacity,
+    data: &usvg::PathData,
+    transform: Affine,
+) -> Option<Paint> {
+    let id = match *paint {
+        usvg::Paint::Color(color) => {
+            return Some(Paint::Color(Color::rgba(
+                color.red,
+                color.green,
+                color.blue,
+                opacity.to_u8(),
+            )));
+        }
+        usvg::Paint::Link(ref id) => id,
+    };
+
+    let node = tree.defs_by_id(id)?;
+    let (kind, base) = match *node.borrow() {
+        usvg::NodeKind::LinearGradient(ref g) => (
+            GradientKind::Linear {
+                start: Point::new(g.x1 as f32, g.y1 as f32),
+                end: Point::new(g.x2 as f32, g.y2 as f32),
+            },
+            g.base.clone(),
+        ),
+        usvg::NodeKind::RadialGradient(ref g) => (
+            GradientKind::Radial {
+                center: Point::new(g.cx as f32, g.cy as f32),
+                radius: g.r.value() as f32,
+                focal: Point::new(g.fx as f32, g.fy as f32),
+            },
+            g.base.clone(),
+        ),
+        _ => return None,
+    };
+
+    // Path points are already in root coordinates, so the gradient is placed relative to them
+    // with the node transform.
+    let bbox_transform = if base.units == usvg::Units::ObjectBoundingBox {
+        let bbox = data.bbox()?;
+        Affine::new([
+            bbox.width() as f32,
+            0.0,
+            bbox.x() as f32,
+            0.0,
+            bbox.height() as f32,
+            bbox.y() as f32,
+        ])
+    } else {
+        Affine::id()
+    };
+    let gradient_transform = transform * bbox_transform * convert_transform(base.transform);
+
+    let stops = base
+        .stops
+        .iter()
+        .map(|stop| {
+            let alpha = stop.opacity.value() * opacity.value();
+            let color = stop.color;
+            Stop::new(
+                stop.offset.value() as f32,
+                Color::rgba(
+                    color.red,
+                    color.green,
+                    color.blue,
+                    (alpha * 255.0).round() as u8,
+                ),
+            )
+        })
+        .collect();
+
+    let extend = match base.spread_method {
+        usvg::SpreadMethod::Pad => Extend::Pad,
+        usvg::SpreadMethod::Reflect => Extend::Reflect,
+        usvg::SpreadMethod::Repeat => Extend::Repeat,
+    };
+
+    Some(Paint::Gradient(Gradient {
+        kind,
+        stops,
+        extend,
+        transform: gradient_transform,
+    }))
 }
 
 pub fn render(commands: &[Command], transform: Affine, target: &mut RenderTarget) {
     for command in commands {
-        match command.style {
-            Style::Fill => {
-                target.fill_path(&command.path, transform, command.color);
-            }
-            Style::Stroke(width) => {
-                target.stroke_path(&command.path, width, transform, command.color);
+        match *command {
+            Command::Path {
+                ref path,
+                ref style,
+                ref paint,
+            } => match *style {
+                Style::Fill(fill_rule) => {
+                    target.fill_path(path, transform, fill_rule, paint, CompositeOp::SrcOver);
+                }
+                Style::Stroke(ref style) => {
+                    target.stroke_path(path, style, transform, paint, CompositeOp::SrcOver);
+                }
+            },
+            Command::Group {
+                opacity,
+                blend,
+                ref mask,
+                ref commands,
+            } => {
+                // The mask only restricts drawing, so the layer is still blended with the content
+                // below the group.
+                let draw = |target: &mut RenderTarget| {
+                    if opacity < 1.0 || blend != BlendMode::Normal {
+                        target.with_layer(opacity, blend, None, |target| {
+                            render(commands, transform, target);
+                        });
+                    } else {
+                        render(commands, transform, target);
+                    }
+                };
+
+                match *mask {
+                    Some(ref mask) => {
+                        let draw_mask = |target: &mut RenderTarget| {
+                            target.with_clip(&mask.rect, transform, FillRule::NonZero, |target| {
+                                render(&mask.commands, transform, target);
+                            });
+                        };
+                        target.with_mask(MaskType::Luminance, draw_mask, draw);
+                    }
+                    None => draw(target),
+                }
             }
         }
     }
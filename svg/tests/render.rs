@@ -0,0 +1,52 @@
+use flicker::{Affine, Renderer};
+
+const SIZE: usize = 4;
+
+fn render(source: &str) -> Vec<u32> {
+    let commands = svg::from_str(source).unwrap();
+    let mut data = vec![0; SIZE * SIZE];
+    let mut renderer = Renderer::new();
+    svg::render(
+        &commands,
+        Affine::id(),
+        &mut renderer.attach(&mut data, SIZE, SIZE),
+    );
+    data
+}
+
+/// Draws an orange background and a gray rectangle over its right half, where `attributes` are
+/// added to a group containing the rectangle and `rect_attributes` to the rectangle itself.
+fn draw_over_orange(attributes: &str, rect_attributes: &str) -> Vec<u32> {
+    render(&format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="4">
+            <rect width="4" height="4" fill="#FF8000"/>
+            <g {}>
+                <rect x="2" width="2" height="4" fill="#808080" {}/>
+            </g>
+        </svg>"##,
+        attributes, rect_attributes
+    ))
+}
+
+#[test]
+fn group_blend_mode() {
+    let normal = draw_over_orange("", "");
+    assert_eq!(normal[0], 0xFFFF8000);
+    assert_eq!(normal[3], 0xFF808080);
+
+    // Multiplying by gray halves each channel of the background.
+    let multiply = 0xFF804000;
+    for attributes in [
+        r#"style="opacity: 1; mix-blend-mode: multiply""#,
+        r#"mix-blend-mode="multiply""#,
+        r#"id="group" mix-blend-mode="multiply""#,
+    ] {
+        let data = draw_over_orange(attributes, "");
+        assert_eq!(data[0], 0xFFFF8000);
+        assert_eq!(data[3], multiply, "{}", attributes);
+    }
+
+    // Blend modes also apply to elements other than groups.
+    let data = draw_over_orange("", r#"mix-blend-mode="multiply""#);
+    assert_eq!(data[3], multiply);
+}
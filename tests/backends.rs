@@ -0,0 +1,257 @@
+use flicker::{
+    Affine, Backend, Blend, BlendMode, Color, CompositeOp, Dash, Extend, FillRule, Filter,
+    Gradient, Image, MaskType, Path, Pattern, Point, RenderTarget, Renderer, Stop, StrokeStyle,
+};
+
+mod common;
+
+use common::{assert_close, backends, rect};
+
+/// Odd sizes, so that rows end in a partial tail on every backend.
+const WIDTH: usize = 53;
+const HEIGHT: usize = 29;
+
+/// Premultiplied pixels of varied color and alpha.
+fn background() -> Vec<u32> {
+    (0..WIDTH * HEIGHT)
+        .map(|i| {
+            let a = (i * 37 % 256) as u32;
+            let [r, g, b] = [11, 97, 173].map(|c| ((i * 59 + c) % 256) as u32 * a / 255);
+            a << 24 | r << 16 | g << 8 | b
+        })
+        .collect()
+}
+
+fn stops() -> Vec<Stop> {
+    vec![
+        Stop::new(0.0, Color::rgba(255, 40, 0, 255)),
+        Stop::new(0.4, Color::rgba(20, 200, 90, 120)),
+        Stop::new(1.0, Color::rgba(30, 60, 250, 210)),
+    ]
+}
+
+/// A closed curved shape which covers most of the target at fractional positions.
+fn blob() -> Path {
+    let mut path = Path::new();
+    path.move_to(Point::new(2.3, 4.7))
+        .cubic_to(
+            Point::new(20.0, -6.0),
+            Point::new(40.0, 3.0),
+            Point::new(51.2, 10.4),
+        )
+        .quadratic_to(Point::new(45.0, 30.0), Point::new(20.6, 27.1))
+        .line_to(Point::new(0.4, 20.9))
+        .close();
+    path
+}
+
+fn render(backend: Backend, linear: bool, draw: &dyn Fn(&mut RenderTarget)) -> Vec<u32> {
+    let mut data = background();
+    let mut renderer = Renderer::new();
+    renderer.set_backend(backend);
+    renderer.set_linear_blending(linear);
+    draw(&mut renderer.attach(&mut data, WIDTH, HEIGHT));
+    data
+}
+
+/// Asserts that every supported backend draws within 1 of the scalar backend, with and without
+/// linear blending.
+fn assert_backends_match(draw: impl Fn(&mut RenderTarget)) {
+    for linear in [false, true] {
+        let expected = render(Backend::Scalar, linear, &draw);
+        for backend in backends() {
+            assert_close(&render(backend, linear, &draw), &expected);
+        }
+    }
+}
+
+#[test]
+fn gradients_match_scalar() {
+    let extends = [Extend::Pad, Extend::Repeat, Extend::Reflect];
+    let center = Point::new(26.0, 14.0);
+    let gradients = [
+        Gradient::linear(Point::new(3.0, 2.0), Point::new(21.0, 17.0), stops()),
+        Gradient::radial(center, 12.0, Some(Point::new(20.0, 10.0)), stops()),
+        Gradient::sweep(center, 0.5, 4.0, stops()),
+    ];
+
+    for gradient in &gradients {
+        for extend in extends {
+            let gradient = Gradient {
+                extend,
+                ..gradient.clone()
+            };
+            assert_backends_match(|target| {
+                let blend = CompositeOp::SrcOver;
+                target.fill_path(&blob(), Affine::id(), FillRule::NonZero, &gradient, blend);
+            });
+        }
+    }
+}
+
+#[test]
+fn blend_modes_match_scalar() {
+    let modes = [
+        BlendMode::Normal,
+        BlendMode::Multiply,
+        BlendMode::Overlay,
+        BlendMode::ColorDodge,
+        BlendMode::ColorBurn,
+        BlendMode::SoftLight,
+        BlendMode::Difference,
+        BlendMode::Hue,
+        BlendMode::Luminosity,
+    ];
+    let ops = [
+        CompositeOp::SrcOver,
+        CompositeOp::SrcAtop,
+        CompositeOp::Xor,
+        CompositeOp::Plus,
+    ];
+    let gradient = Gradient::linear(Point::new(0.0, 0.0), Point::new(53.0, 29.0), stops());
+
+    for mode in modes {
+        for op in ops {
+            assert_backends_match(|target| {
+                let blend = Blend::new(mode, op);
+                target.fill_path(&blob(), Affine::id(), FillRule::EvenOdd, &gradient, blend);
+            });
+        }
+    }
+}
+
+#[test]
+fn images_match_scalar() {
+    let pixels = background();
+    let image = Image::new(&pixels[3..], 17, 9, WIDTH);
+    let transform = Affine::translate(4.5, 2.25) * Affine::rotate(0.3) * Affine::scale(2.3);
+
+    for filter in [Filter::Nearest, Filter::Bilinear, Filter::Bicubic] {
+        for extend in [Extend::Pad, Extend::Repeat, Extend::Reflect] {
+            let pattern = Pattern {
+                image,
+                transform,
+                extend,
+                filter,
+            };
+            assert_backends_match(|target| {
+                let blend = CompositeOp::SrcOver;
+                target.fill_path(&blob(), Affine::id(), FillRule::NonZero, pattern, blend);
+            });
+        }
+    }
+}
+
+#[test]
+fn clips_layers_and_strokes_match_scalar() {
+    let mut style = StrokeStyle::new(2.7);
+    style.dash = Some(Dash::new(vec![5.0, 3.5], 1.25));
+    let clip = rect(4.3, 2.6, 47.8, 25.1);
+
+    assert_backends_match(|target| {
+        let color = Color::rgba(230, 120, 40, 180);
+        target.with_clip(&clip, Affine::id(), FillRule::NonZero, |target| {
+            target.with_layer(0.6, BlendMode::Screen, None, |target| {
+                target.stroke_path(&blob(), &style, Affine::id(), color, CompositeOp::SrcOver);
+            });
+            target.with_mask(
+                MaskType::Luminance,
+                |target| {
+                    let white = Color::rgba(255, 255, 255, 160);
+                    target.fill_path(
+                        &blob(),
+                        Affine::id(),
+                        FillRule::NonZero,
+                        white,
+                        BlendMode::Normal,
+                    );
+                },
+                |target| {
+                    let full = rect(0.0, 0.0, WIDTH as f32, HEIGHT as f32);
+                    target.fill_path(
+                        &full,
+                        Affine::id(),
+                        FillRule::NonZero,
+                        color,
+                        CompositeOp::SrcOver,
+                    );
+                },
+            );
+        });
+    });
+}
+
+#[test]
+fn span_tails_match_scalar() {
+    // In each row, a triangle with a shallow edge partially covers a span of a different length
+    // and alignment, so that the edge loop ends in a tail of every length on every backend.
+    let mut path = Path::new();
+    for y in 0..HEIGHT {
+        let len = (y + 1) as f32 * 1.5;
+        let x0 = (y * 7 % 13) as f32 + 0.3;
+        let y = y as f32;
+        path.move_to(Point::new(x0, y))
+            .line_to(Point::new(x0 + len, y + 1.0))
+            .line_to(Point::new(x0, y + 1.0))
+            .close();
+    }
+    let clip = rect(1.5, 0.0, WIDTH as f32 - 2.5, HEIGHT as f32);
+    let gradient = Gradient::linear(Point::new(0.0, 0.0), Point::new(53.0, 0.0), stops());
+
+    for clipped in [false, true] {
+        assert_backends_match(|target| {
+            let draw = |target: &mut RenderTarget| {
+                let color = Color::rgba(200, 100, 50, 255);
+                let blend = CompositeOp::SrcOver;
+                target.fill_path(&path, Affine::id(), FillRule::NonZero, color, blend);
+                target.fill_path(&path, Affine::id(), FillRule::NonZero, &gradient, blend);
+            };
+            if clipped {
+                target.with_clip(&clip, Affine::id(), FillRule::NonZero, draw);
+            } else {
+                draw(target);
+            }
+        });
+    }
+}
+
+/// Opaque pixels with every value in each color channel, followed by every alpha with colors
+/// equal to it.
+fn levels() -> Vec<u32> {
+    (0..256)
+        .map(|i| 0xFF00_0000 | i << 16 | (255 - i) << 8 | (i * 7 % 256))
+        .chain((0..256).map(|i| i * 0x0101_0101))
+        .collect()
+}
+
+fn fill_levels(backend: Backend, color: Color, op: CompositeOp) -> Vec<u32> {
+    let mut data = levels();
+    let width = data.len();
+    let mut renderer = Renderer::new();
+    renderer.set_backend(backend);
+    let mut target = renderer.attach(&mut data, width, 1);
+    let path = rect(0.0, 0.0, width as f32, 1.0);
+    target.fill_path(&path, Affine::id(), FillRule::NonZero, color, op);
+    drop(target);
+    data
+}
+
+#[test]
+fn levels_round_trip_exactly() {
+    let opaque = Color::rgba(0, 0, 0, 255);
+    for backend in backends() {
+        let data = fill_levels(backend, opaque, CompositeOp::DstIn);
+        assert_eq!(data, levels(), "{:?}", backend);
+    }
+}
+
+#[test]
+fn packing_rounds_like_scalar() {
+    // Blending with half alpha puts many results halfway between 8-bit values.
+    let color = Color::rgba(255, 128, 1, 128);
+    let expected = fill_levels(Backend::Scalar, color, CompositeOp::SrcOver);
+    for backend in backends() {
+        let data = fill_levels(backend, color, CompositeOp::SrcOver);
+        assert_eq!(data, expected, "{:?}", backend);
+    }
+}
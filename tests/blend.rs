@@ -0,0 +1,248 @@
+use flicker::{Affine, Blend, BlendMode, Color, CompositeOp, FillRule, Renderer};
+
+mod common;
+
+use common::{assert_close, backends, rect};
+
+const OPS: [CompositeOp; 12] = [
+    CompositeOp::Clear,
+    CompositeOp::Src,
+    CompositeOp::SrcOver,
+    CompositeOp::SrcIn,
+    CompositeOp::SrcOut,
+    CompositeOp::SrcAtop,
+    CompositeOp::DstOver,
+    CompositeOp::DstIn,
+    CompositeOp::DstOut,
+    CompositeOp::DstAtop,
+    CompositeOp::Xor,
+    CompositeOp::Plus,
+];
+
+const MODES: [BlendMode; 16] = [
+    BlendMode::Normal,
+    BlendMode::Multiply,
+    BlendMode::Screen,
+    BlendMode::Overlay,
+    BlendMode::Darken,
+    BlendMode::Lighten,
+    BlendMode::ColorDodge,
+    BlendMode::ColorBurn,
+    BlendMode::HardLight,
+    BlendMode::SoftLight,
+    BlendMode::Difference,
+    BlendMode::Exclusion,
+    BlendMode::Hue,
+    BlendMode::Saturation,
+    BlendMode::Color,
+    BlendMode::Luminosity,
+];
+
+fn sources() -> [Color; 5] {
+    [
+        Color::rgba(200, 100, 50, 255),
+        Color::rgba(30, 220, 160, 128),
+        Color::rgba(255, 255, 255, 200),
+        Color::rgba(0, 0, 0, 255),
+        Color::rgba(90, 40, 250, 20),
+    ]
+}
+
+/// Premultiplied destination pixels, covering transparent, opaque, and partially transparent
+/// colors.
+const DESTINATIONS: [u32; 7] = [
+    0x00000000, 0xFF336699, 0x80402010, 0xFFFFFFFF, 0xFF000000, 0x40102030, 0xC0A08060,
+];
+
+/// Long enough for a full vector and a partial tail on every backend.
+const WIDTH: usize = 37;
+
+/// The separable blend functions of the W3C Compositing and Blending specification.
+fn separable(mode: BlendMode, cb: f64, cs: f64) -> f64 {
+    let multiply = |cb: f64, cs: f64| cb * cs;
+    let screen = |cb: f64, cs: f64| cb + cs - cb * cs;
+    let hard_light = |cb: f64, cs: f64| {
+        if cs <= 0.5 {
+            multiply(cb, 2.0 * cs)
+        } else {
+            screen(cb, 2.0 * cs - 1.0)
+        }
+    };
+
+    match mode {
+        BlendMode::Normal => cs,
+        BlendMode::Multiply => multiply(cb, cs),
+        BlendMode::Screen => screen(cb, cs),
+        BlendMode::Overlay => hard_light(cs, cb),
+        BlendMode::Darken => cb.min(cs),
+        BlendMode::Lighten => cb.max(cs),
+        BlendMode::ColorDodge => {
+            if cb == 0.0 {
+                0.0
+            } else if cs == 1.0 {
+                1.0
+            } else {
+                (cb / (1.0 - cs)).min(1.0)
+            }
+        }
+        BlendMode::ColorBurn => {
+            if cb == 1.0 {
+                1.0
+            } else if cs == 0.0 {
+                0.0
+            } else {
+                1.0 - ((1.0 - cb) / cs).min(1.0)
+            }
+        }
+        BlendMode::HardLight => hard_light(cb, cs),
+        BlendMode::SoftLight => {
+            if cs <= 0.5 {
+                cb - (1.0 - 2.0 * cs) * cb * (1.0 - cb)
+            } else {
+                let d = if cb <= 0.25 {
+                    ((16.0 * cb - 12.0) * cb + 4.0) * cb
+                } else {
+                    cb.sqrt()
+                };
+                cb + (2.0 * cs - 1.0) * (d - cb)
+            }
+        }
+        BlendMode::Difference => (cb - cs).abs(),
+        BlendMode::Exclusion => cb + cs - 2.0 * cb * cs,
+        _ => unreachable!(),
+    }
+}
+
+fn lum(c: [f64; 3]) -> f64 {
+    0.3 * c[0] + 0.59 * c[1] + 0.11 * c[2]
+}
+
+fn sat(c: [f64; 3]) -> f64 {
+    c[0].max(c[1]).max(c[2]) - c[0].min(c[1]).min(c[2])
+}
+
+fn clip_color(c: [f64; 3]) -> [f64; 3] {
+    let l = lum(c);
+    let n = c[0].min(c[1]).min(c[2]);
+    let x = c[0].max(c[1]).max(c[2]);
+    let c = if n < 0.0 {
+        c.map(|c| l + (c - l) * l / (l - n))
+    } else {
+        c
+    };
+    if x > 1.0 {
+        c.map(|c| l + (c - l) * (1.0 - l) / (x - l))
+    } else {
+        c
+    }
+}
+
+fn set_lum(c: [f64; 3], l: f64) -> [f64; 3] {
+    let d = l - lum(c);
+    clip_color(c.map(|c| c + d))
+}
+
+fn set_sat(c: [f64; 3], s: f64) -> [f64; 3] {
+    let min = c[0].min(c[1]).min(c[2]);
+    let range = sat(c);
+    if range > 0.0 {
+        c.map(|c| (c - min) * s / range)
+    } else {
+        [0.0; 3]
+    }
+}
+
+fn mix(mode: BlendMode, cb: [f64; 3], cs: [f64; 3]) -> [f64; 3] {
+    match mode {
+        BlendMode::Hue => set_lum(set_sat(cs, sat(cb)), lum(cb)),
+        BlendMode::Saturation => set_lum(set_sat(cb, sat(cs)), lum(cb)),
+        BlendMode::Color => set_lum(cs, lum(cb)),
+        BlendMode::Luminosity => set_lum(cb, lum(cs)),
+        _ => [0, 1, 2].map(|c| separable(mode, cb[c], cs[c])),
+    }
+}
+
+/// Blends and composites an unpremultiplied source color onto a premultiplied pixel, following
+/// the specification in double precision.
+fn reference(blend: Blend, color: Color, dst: u32) -> u32 {
+    let [ab, rb, gb, bb] = [24, 16, 8, 0].map(|shift| (dst >> shift & 0xFF) as f64 / 255.0);
+    let dst = [ab, rb, gb, bb];
+    let cb = [rb, gb, bb].map(|c| if ab > 0.0 { c / ab } else { 0.0 });
+
+    let a_s = color.a() as f64 / 255.0;
+    let cs = [color.r(), color.g(), color.b()].map(|c| c as f64 / 255.0);
+
+    let mixed = mix(blend.mode, cb, cs);
+    let [r, g, b] = [0, 1, 2].map(|c| a_s * ((1.0 - ab) * cs[c] + ab * mixed[c]));
+    let src = [a_s, r, g, b];
+
+    let (fa, fb) = match blend.op {
+        CompositeOp::Clear => (0.0, 0.0),
+        CompositeOp::Src => (1.0, 0.0),
+        CompositeOp::SrcOver => (1.0, 1.0 - a_s),
+        CompositeOp::SrcIn => (ab, 0.0),
+        CompositeOp::SrcOut => (1.0 - ab, 0.0),
+        CompositeOp::SrcAtop => (ab, 1.0 - a_s),
+        CompositeOp::DstOver => (1.0 - ab, 1.0),
+        CompositeOp::DstIn => (0.0, a_s),
+        CompositeOp::DstOut => (0.0, 1.0 - a_s),
+        CompositeOp::DstAtop => (1.0 - ab, a_s),
+        CompositeOp::Xor => (1.0 - ab, 1.0 - a_s),
+        CompositeOp::Plus => (1.0, 1.0),
+    };
+
+    let mut out = 0;
+    for c in 0..4 {
+        let value = (src[c] * fa + dst[c] * fb).min(1.0);
+        out |= ((255.0 * value).round() as u32) << (24 - 8 * c);
+    }
+    out
+}
+
+fn check(blend: Blend) {
+    let background: Vec<u32> = (0..WIDTH).map(|i| DESTINATIONS[i % DESTINATIONS.len()]).collect();
+
+    for backend in backends() {
+        for color in sources() {
+            let mut data = background.clone();
+            let mut renderer = Renderer::new();
+            renderer.set_backend(backend);
+            let mut target = renderer.attach(&mut data, WIDTH, 1);
+            let path = rect(0.0, 0.0, WIDTH as f32, 1.0);
+            target.fill_path(&path, Affine::id(), FillRule::NonZero, color, blend);
+            drop(target);
+
+            let expected: Vec<u32> =
+                background.iter().map(|&dst| reference(blend, color, dst)).collect();
+            assert_close(&data, &expected);
+        }
+    }
+}
+
+#[test]
+fn composite_ops() {
+    for op in OPS {
+        check(Blend::new(BlendMode::Normal, op));
+    }
+}
+
+#[test]
+fn blend_modes() {
+    for mode in MODES {
+        check(Blend::new(mode, CompositeOp::SrcOver));
+    }
+}
+
+#[test]
+fn blend_modes_with_composite_ops() {
+    for mode in [BlendMode::Multiply, BlendMode::SoftLight, BlendMode::Hue] {
+        for op in [
+            CompositeOp::SrcIn,
+            CompositeOp::DstAtop,
+            CompositeOp::Xor,
+            CompositeOp::Plus,
+        ] {
+            check(Blend::new(mode, op));
+        }
+    }
+}
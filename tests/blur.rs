@@ -0,0 +1,118 @@
+use flicker::{Affine, Color, CompositeOp, FillRule, Point, RenderTarget, Renderer};
+
+mod common;
+
+use common::{assert_close, channels, rect};
+
+const SIZE: usize = 31;
+
+fn render(background: u32, draw: impl Fn(&mut RenderTarget)) -> Vec<u32> {
+    let mut data = vec![background; SIZE * SIZE];
+    let mut renderer = Renderer::new();
+    draw(&mut renderer.attach(&mut data, SIZE, SIZE));
+    data
+}
+
+fn alpha(data: &[u32], x: usize, y: usize) -> i32 {
+    channels(data[y * SIZE + x])[0]
+}
+
+#[test]
+fn blur_spreads_a_pixel_symmetrically() {
+    let white = Color::rgba(255, 255, 255, 255);
+    let data = render(0, |target| {
+        target.with_blur(6.0, |target| {
+            let path = rect(15.0, 15.0, 16.0, 16.0);
+            target.fill_path(
+                &path,
+                Affine::id(),
+                FillRule::NonZero,
+                white,
+                CompositeOp::SrcOver,
+            );
+        });
+    });
+
+    // The falloff is the same in every direction from the pixel, and decreases away from it.
+    let center = alpha(&data, 15, 15);
+    assert!(center < 255);
+    for dy in 0..15 {
+        for dx in 0..15 {
+            let value = alpha(&data, 15 + dx, 15 + dy);
+            for (x, y) in [(15 - dx, 15 + dy), (15 + dx, 15 - dy), (15 + dy, 15 + dx)] {
+                assert!((alpha(&data, x, y) - value).abs() <= 1, "({}, {})", x, y);
+            }
+            if dx > 0 {
+                assert!(value <= alpha(&data, 14 + dx, 15 + dy));
+            }
+        }
+    }
+    assert!(alpha(&data, 18, 15) > 0);
+}
+
+#[test]
+fn zero_blur_radius_draws_unchanged() {
+    let draw = |target: &mut RenderTarget| {
+        let path = rect(3.3, 4.6, 20.2, 17.9);
+        let color = Color::rgba(200, 100, 50, 160);
+        target.fill_path(
+            &path,
+            Affine::id(),
+            FillRule::NonZero,
+            color,
+            CompositeOp::SrcOver,
+        );
+    };
+
+    let expected = render(0xFF20_4060, draw);
+    for radius in [0.0, 0.1, -1.0] {
+        let blurred = render(0xFF20_4060, |target| target.with_blur(radius, draw));
+        assert!(blurred == expected, "{}", radius);
+    }
+}
+
+#[test]
+fn drop_shadow_is_offset_beneath_path() {
+    let path = rect(4.0, 4.0, 10.0, 10.0);
+    let shadow = Color::rgba(0, 0, 0, 255);
+    let red = Color::rgba(255, 0, 0, 255);
+    let data = render(0xFFFF_FFFF, |target| {
+        target.drop_shadow(&path, Point::new(3.0, 2.0), 0.0, shadow);
+        target.fill_path(
+            &path,
+            Affine::id(),
+            FillRule::NonZero,
+            red,
+            CompositeOp::SrcOver,
+        );
+    });
+
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let in_path = (4..10).contains(&x) && (4..10).contains(&y);
+            let in_shadow = (7..13).contains(&x) && (6..12).contains(&y);
+            let expected = match (in_path, in_shadow) {
+                (true, _) => 0xFFFF_0000,
+                (false, true) => 0xFF00_0000,
+                (false, false) => 0xFFFF_FFFF,
+            };
+            assert_eq!(data[y * SIZE + x], expected, "({}, {})", x, y);
+        }
+    }
+
+    // A blurred shadow is centered on the offset path, at (10, 9).
+    let data = render(0, |target| {
+        target.drop_shadow(&path, Point::new(3.0, 2.0), 5.0, shadow);
+    });
+    let mirrored: Vec<u32> = (0..SIZE * SIZE)
+        .map(|i| {
+            let (x, y) = (i % SIZE, i / SIZE);
+            match (19usize.checked_sub(x), 17usize.checked_sub(y)) {
+                (Some(x), Some(y)) if x < SIZE && y < SIZE => data[y * SIZE + x],
+                _ => 0,
+            }
+        })
+        .collect();
+    assert_close(&data[..17 * SIZE], &mirrored[..17 * SIZE]);
+    assert!(alpha(&data, 10, 9) > alpha(&data, 6, 9));
+}
@@ -0,0 +1,96 @@
+use flicker::{Affine, Bbox, Color, CompositeOp, FillRule, Path, Point, RenderTarget, Renderer};
+
+mod common;
+
+use common::rect;
+
+const WIDTH: usize = 32;
+const HEIGHT: usize = 24;
+
+fn render(draw: impl Fn(&mut RenderTarget)) -> Vec<u32> {
+    let mut data = vec![0xFF10_2030; WIDTH * HEIGHT];
+    let mut renderer = Renderer::new();
+    draw(&mut renderer.attach(&mut data, WIDTH, HEIGHT));
+    data
+}
+
+/// Fills a triangle with fractional vertices, whose edges cross the clip at partially covered
+/// pixels.
+fn fill_triangle(target: &mut RenderTarget) {
+    let mut path = Path::new();
+    path.move_to(Point::new(1.3, 0.6))
+        .line_to(Point::new(30.2, 5.7))
+        .line_to(Point::new(8.6, 23.1))
+        .close();
+    let color = Color::rgba(200, 120, 40, 220);
+    target.fill_path(
+        &path,
+        Affine::id(),
+        FillRule::NonZero,
+        color,
+        CompositeOp::SrcOver,
+    );
+}
+
+#[test]
+fn clip_rect_matches_path_clip() {
+    let bbox = Bbox {
+        x0: 5,
+        y0: 3,
+        x1: 22,
+        y1: 17,
+    };
+    let clip_rect = render(|target| target.with_clip_rect(bbox, fill_triangle));
+    let clip_path = render(|target| {
+        let path = rect(5.0, 3.0, 22.0, 17.0);
+        target.with_clip(&path, Affine::id(), FillRule::NonZero, fill_triangle);
+    });
+    assert_eq!(clip_rect, clip_path);
+
+    // The triangle's edges are drawn with the same partial coverage inside the clip, and nothing
+    // is drawn outside it.
+    let unclipped = render(fill_triangle);
+    let background = render(|_| {});
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let i = y * WIDTH + x;
+            let inside = (5..22).contains(&x) && (3..17).contains(&y);
+            let expected = if inside { unclipped[i] } else { background[i] };
+            assert_eq!(clip_rect[i], expected, "({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn nested_clip_rects_intersect() {
+    let outer = Bbox {
+        x0: 2,
+        y0: 4,
+        x1: 20,
+        y1: 15,
+    };
+    let inner = Bbox {
+        x0: 9,
+        y0: 1,
+        x1: 28,
+        y1: 11,
+    };
+    let nested = render(|target| {
+        target.with_clip_rect(outer, |target| {
+            target.with_clip_rect(inner, fill_triangle);
+        });
+    });
+    let intersection =
+        render(|target| target.with_clip_rect(outer.intersect(inner), fill_triangle));
+    assert_eq!(nested, intersection);
+
+    // The outer clip is restored after the inner one ends.
+    let after_inner = render(|target| {
+        target.with_clip_rect(outer, |target| {
+            target.with_clip_rect(inner, |_| {});
+            fill_triangle(target);
+        });
+    });
+    let outer_only = render(|target| target.with_clip_rect(outer, fill_triangle));
+    assert_eq!(after_inner, outer_only);
+}
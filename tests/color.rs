@@ -0,0 +1,105 @@
+use flicker::{
+    Affine, Color, CompositeOp, FillRule, Filter, Image, Pattern, PremulColor, Renderer,
+};
+
+mod common;
+
+use common::{backends, rect};
+
+/// Long enough for a full vector and a partial tail on every backend.
+const WIDTH: usize = 37;
+
+fn colors() -> Vec<PremulColor> {
+    (0..WIDTH)
+        .map(|i| {
+            let a = if i % 5 == 1 {
+                255
+            } else {
+                (i * 71 % 256) as u8
+            };
+            let [r, g, b] = [17, 106, 195].map(|c| ((i * 53 + c) % 256) as u8);
+            PremulColor::rgba(r, g, b, a)
+        })
+        .collect()
+}
+
+#[test]
+fn premultiply_rounds_to_nearest() {
+    let color = Color::rgba(200, 100, 50, 128);
+    assert_eq!(color.premultiply(), PremulColor::rgba(100, 50, 25, 128));
+    assert_eq!(
+        color.premultiply().unpremultiply(),
+        Color::rgba(199, 100, 50, 128)
+    );
+
+    // Channels are clamped to alpha, and transparent colors unpremultiply to transparent black.
+    assert_eq!(
+        PremulColor::from(0x80FF_4020),
+        PremulColor::rgba(128, 64, 32, 128)
+    );
+    assert_eq!(
+        PremulColor::rgba(0, 0, 0, 0).unpremultiply(),
+        Color::rgba(0, 0, 0, 0)
+    );
+}
+
+#[test]
+fn clear_writes_premultiplied_colors() {
+    for backend in backends() {
+        let mut renderer = Renderer::new();
+        renderer.set_backend(backend);
+
+        let color = Color::rgba(200, 100, 50, 128);
+        let mut data = vec![PremulColor::rgba(0, 0, 0, 0); WIDTH];
+        renderer.attach_colors(&mut data, WIDTH, 1).clear(color);
+        assert_eq!(data, vec![color.premultiply(); WIDTH]);
+
+        let premul = PremulColor::rgba(30, 60, 90, 100);
+        renderer.attach_colors(&mut data, WIDTH, 1).clear(premul);
+        assert_eq!(data, vec![premul; WIDTH]);
+    }
+}
+
+#[test]
+fn image_colors_are_copied_unchanged() {
+    let src = colors();
+    let image = Image::from_colors(&src, WIDTH, 1, WIDTH);
+    let mut pattern = Pattern::new(image);
+    pattern.filter = Filter::Nearest;
+
+    for backend in backends() {
+        let mut data = vec![PremulColor::rgba(0, 0, 0, 0); WIDTH];
+        let mut renderer = Renderer::new();
+        renderer.set_backend(backend);
+        let mut target = renderer.attach_colors(&mut data, WIDTH, 1);
+        let path = rect(0.0, 0.0, WIDTH as f32, 1.0);
+        target.fill_path(
+            &path,
+            Affine::id(),
+            FillRule::NonZero,
+            pattern,
+            CompositeOp::Src,
+        );
+        drop(target);
+
+        assert_eq!(data, src);
+    }
+}
+
+#[test]
+fn straight_alpha_export_unpremultiplies() {
+    let src = colors();
+
+    for backend in backends() {
+        let mut data = src.clone();
+        let mut renderer = Renderer::new();
+        renderer.set_backend(backend);
+        let target = renderer.attach_colors(&mut data, WIDTH, 1);
+        let mut straight = vec![0; WIDTH];
+        target.to_straight_alpha(&mut straight);
+
+        for (&actual, &color) in straight.iter().zip(&src) {
+            assert_eq!(Color::from(actual), color.unpremultiply());
+        }
+    }
+}
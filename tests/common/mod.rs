@@ -0,0 +1,41 @@
+#![allow(dead_code)]
+
+use flicker::{Backend, Path, Point};
+
+const BACKENDS: [Backend; 6] = [
+    Backend::Scalar,
+    Backend::Sse2,
+    Backend::Avx2,
+    Backend::Avx512,
+    Backend::Neon,
+    Backend::Wasm128,
+];
+
+/// The backends supported by the current CPU.
+pub fn backends() -> impl Iterator<Item = Backend> {
+    BACKENDS.into_iter().filter(|backend| backend.is_supported())
+}
+
+pub fn rect(x0: f32, y0: f32, x1: f32, y1: f32) -> Path {
+    let mut path = Path::new();
+    path.move_to(Point::new(x0, y0))
+        .line_to(Point::new(x1, y0))
+        .line_to(Point::new(x1, y1))
+        .line_to(Point::new(x0, y1))
+        .close();
+    path
+}
+
+/// Splits a pixel into its a, r, g, and b channels.
+pub fn channels(pixel: u32) -> [i32; 4] {
+    [24, 16, 8, 0].map(|shift| (pixel >> shift & 0xFF) as i32)
+}
+
+/// Asserts that each channel of each pixel is within 1 of the expected value.
+pub fn assert_close(actual: &[u32], expected: &[u32]) {
+    for (i, (&actual, &expected)) in actual.iter().zip(expected).enumerate() {
+        let close =
+            channels(actual).iter().zip(channels(expected)).all(|(a, e)| (a - e).abs() <= 1);
+        assert!(close, "pixel {}: {:08X} != {:08X}", i, actual, expected);
+    }
+}
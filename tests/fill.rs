@@ -0,0 +1,166 @@
+use flicker::{Affine, Color, CompositeOp, FillRule, Point, Renderer};
+
+mod common;
+
+use common::{assert_close, backends, channels, rect};
+
+/// Composites a color with coverage `cvg` over a premultiplied pixel, rounding to nearest.
+fn src_over(color: Color, cvg: f32, dst: u32) -> u32 {
+    let a = color.a() as f32 / 255.0;
+    let src = [255.0, color.r() as f32, color.g() as f32, color.b() as f32].map(|c| a * c);
+    let inv_a = 1.0 - cvg * a;
+
+    let dst = channels(dst);
+    let mut out = 0;
+    for c in 0..4 {
+        let value = (cvg * src[c] + inv_a * dst[c] as f32).round() as u32;
+        out |= value << (24 - 8 * c);
+    }
+    out
+}
+
+const WIDTH: usize = 40;
+const HEIGHT: usize = 40;
+
+fn background() -> Vec<u32> {
+    (0..WIDTH * HEIGHT)
+        .map(|i| match i % 3 {
+            0 => 0xFF336699,
+            1 => 0x80402010,
+            _ => 0x00000000,
+        })
+        .collect()
+}
+
+#[test]
+fn solid_fill_matches_reference() {
+    let colors = [
+        Color::rgba(255, 0, 0, 255),
+        Color::rgba(200, 100, 50, 128),
+        Color::rgba(10, 250, 90, 3),
+    ];
+
+    for backend in backends() {
+        for &color in &colors {
+            let mut data = background();
+            let mut expected = background();
+
+            let mut renderer = Renderer::new();
+            renderer.set_backend(backend);
+            let mut target = renderer.attach(&mut data, WIDTH, HEIGHT);
+
+            // Each row covers pixels `y / 2` through `y` fully, with half coverage on either side,
+            // so that spans of many lengths and offsets exercise both full vectors and partial
+            // tails.
+            for y in 0..HEIGHT {
+                let (x0, x1) = ((y / 2) as f32 - 0.5, y as f32 + 1.5);
+                let path = rect(x0, y as f32, x1, y as f32 + 1.0);
+                target.fill_path(
+                    &path,
+                    Affine::id(),
+                    FillRule::NonZero,
+                    color,
+                    CompositeOp::SrcOver,
+                );
+
+                for x in 0..WIDTH {
+                    let cvg = (x1.min(x as f32 + 1.0) - x0.max(x as f32)).clamp(0.0, 1.0);
+                    if cvg > 0.0 {
+                        let pixel = &mut expected[y * WIDTH + x];
+                        *pixel = src_over(color, cvg, *pixel);
+                    }
+                }
+            }
+
+            drop(target);
+            assert_close(&data, &expected);
+        }
+    }
+}
+
+#[test]
+fn solid_fill_with_clip_matches_fill_without() {
+    let color = Color::rgba(200, 100, 50, 128);
+    let path = rect(0.5, 0.5, WIDTH as f32 - 3.25, HEIGHT as f32 - 0.5);
+
+    for backend in backends() {
+        let mut renderer = Renderer::new();
+        renderer.set_backend(backend);
+
+        let mut unclipped = background();
+        let mut target = renderer.attach(&mut unclipped, WIDTH, HEIGHT);
+        target.fill_path(
+            &path,
+            Affine::id(),
+            FillRule::NonZero,
+            color,
+            CompositeOp::SrcOver,
+        );
+        drop(target);
+
+        let mut clipped = background();
+        let mut target = renderer.attach(&mut clipped, WIDTH, HEIGHT);
+        let clip = rect(0.0, 0.0, WIDTH as f32, HEIGHT as f32);
+        target.with_clip(&clip, Affine::id(), FillRule::NonZero, |target| {
+            target.fill_path(
+                &path,
+                Affine::id(),
+                FillRule::NonZero,
+                color,
+                CompositeOp::SrcOver,
+            );
+        });
+        drop(target);
+
+        assert_close(&clipped, &unclipped);
+    }
+}
+
+#[test]
+fn fill_rules() {
+    const SIZE: usize = 20;
+
+    // Two nested squares with the same winding direction, where the inner one has edges halfway
+    // through pixels 5 and 14.
+    let mut path = rect(0.0, 0.0, SIZE as f32, SIZE as f32);
+    path.move_to(Point::new(5.5, 5.5))
+        .line_to(Point::new(14.5, 5.5))
+        .line_to(Point::new(14.5, 14.5))
+        .line_to(Point::new(5.5, 14.5))
+        .close();
+
+    let coverage = |fill_rule: FillRule, x: usize, y: usize| -> f32 {
+        let inside = |i: usize| match i {
+            5 | 14 => 0.5,
+            6..=13 => 1.0,
+            _ => 0.0,
+        };
+        let inner = inside(x) * inside(y);
+        match fill_rule {
+            FillRule::NonZero => 1.0,
+            FillRule::EvenOdd => 1.0 - inner,
+        }
+    };
+
+    for backend in backends() {
+        for fill_rule in [FillRule::NonZero, FillRule::EvenOdd] {
+            let mut data = vec![0; SIZE * SIZE];
+            let mut renderer = Renderer::new();
+            renderer.set_backend(backend);
+            let mut target = renderer.attach(&mut data, SIZE, SIZE);
+            let color = Color::rgba(255, 255, 255, 255);
+            target.fill_path(&path, Affine::id(), fill_rule, color, CompositeOp::SrcOver);
+            drop(target);
+
+            let mut expected = vec![0; SIZE * SIZE];
+            for y in 0..SIZE {
+                for x in 0..SIZE {
+                    let value = (255.0 * coverage(fill_rule, x, y)).round() as u32;
+                    expected[y * SIZE + x] = value * 0x01010101;
+                }
+            }
+
+            assert_close(&data, &expected);
+        }
+    }
+}
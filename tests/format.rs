@@ -0,0 +1,373 @@
+use flicker::{
+    Affine, AlphaMode, Backend, Color, ColorF32, CompositeOp, FillRule, Gradient, Paint, Pixels,
+    Point, RenderTarget, Renderer, Stop,
+};
+
+mod common;
+
+use common::{backends, rect};
+
+/// Long enough for a full vector and a partial tail on every backend.
+const WIDTH: usize = 37;
+
+/// Fills a row of pixels with `paint`.
+fn fill<'p>(backend: Backend, pixels: Pixels, paint: impl Into<Paint<'p>>, op: CompositeOp) {
+    let mut renderer = Renderer::new();
+    renderer.set_backend(backend);
+    let mut target = renderer.attach_pixels(pixels, WIDTH, 1);
+    let path = rect(0.0, 0.0, WIDTH as f32, 1.0);
+    target.fill_path(&path, Affine::id(), FillRule::NonZero, paint, op);
+}
+
+/// Loads and stores each pixel without changing it, by keeping the destination where an opaque
+/// source covers it.
+fn round_trip(backend: Backend, pixels: Pixels) {
+    let opaque = Color::rgba(0, 0, 0, 255);
+    fill(backend, pixels, opaque, CompositeOp::DstIn);
+}
+
+fn assert_bytes_close(actual: &[u8], expected: &[u8]) {
+    for (i, (&actual, &expected)) in actual.iter().zip(expected).enumerate() {
+        let close = (actual as i32 - expected as i32).abs() <= 1;
+        assert!(close, "byte {}: {} != {}", i, actual, expected);
+    }
+}
+
+/// Four bytes per pixel with varied alpha, where colors are at most alpha if `premultiplied` is
+/// set, and zero where alpha is zero.
+fn pattern(premultiplied: bool) -> Vec<u8> {
+    let mut data = Vec::new();
+    for i in 0..WIDTH {
+        let a = if i % 5 == 1 { 255 } else { i * 71 % 256 };
+        for c in 0..3 {
+            let value = (i * 53 + c * 89 + 17) % 256;
+            let value = if premultiplied {
+                value * a / 255
+            } else {
+                value * a.min(1)
+            };
+            data.push(value as u8);
+        }
+        data.push(a as u8);
+    }
+    data
+}
+
+#[test]
+fn byte_formats_round_trip() {
+    for backend in backends() {
+        for mode in [AlphaMode::Premultiplied, AlphaMode::Straight] {
+            let expected = pattern(mode == AlphaMode::Premultiplied);
+
+            let mut data = expected.clone();
+            round_trip(backend, Pixels::Rgba8(&mut data, mode));
+            assert_bytes_close(&data, &expected);
+
+            let mut data = expected.clone();
+            round_trip(backend, Pixels::Bgra8(&mut data, mode));
+            assert_bytes_close(&data, &expected);
+        }
+
+        let expected: Vec<u8> = (0..WIDTH).map(|i| (i * 71 % 256) as u8).collect();
+        let mut data = expected.clone();
+        round_trip(backend, Pixels::A8(&mut data));
+        assert_eq!(data, expected);
+
+        let expected: Vec<u16> = (0..WIDTH).map(|i| (i * 40503 % 65536) as u16).collect();
+        let mut data = expected.clone();
+        round_trip(backend, Pixels::Rgb565(&mut data));
+        assert_eq!(data, expected);
+    }
+}
+
+#[test]
+fn byte_formats_store_channels_in_order() {
+    let color = Color::rgba(200, 100, 50, 128);
+    let repeat = |pixel: [u8; 4]| pixel.repeat(WIDTH);
+
+    for backend in backends() {
+        let cases = [
+            (
+                AlphaMode::Premultiplied,
+                [100, 50, 25, 128],
+                [25, 50, 100, 128],
+            ),
+            (
+                AlphaMode::Straight,
+                [200, 100, 50, 128],
+                [50, 100, 200, 128],
+            ),
+        ];
+        for (mode, rgba, bgra) in cases {
+            let mut data = vec![0; 4 * WIDTH];
+            fill(
+                backend,
+                Pixels::Rgba8(&mut data, mode),
+                color,
+                CompositeOp::Src,
+            );
+            assert_bytes_close(&data, &repeat(rgba));
+
+            let mut data = vec![0; 4 * WIDTH];
+            fill(
+                backend,
+                Pixels::Bgra8(&mut data, mode),
+                color,
+                CompositeOp::Src,
+            );
+            assert_bytes_close(&data, &repeat(bgra));
+        }
+
+        let mut data = vec![0; WIDTH];
+        fill(backend, Pixels::A8(&mut data), color, CompositeOp::Src);
+        assert_eq!(data, vec![128; WIDTH]);
+
+        // 200, 100, and 50 round to 24 of 31, 25 of 63, and 6 of 31.
+        let mut data = vec![0; WIDTH];
+        let opaque = Color::rgba(200, 100, 50, 255);
+        fill(backend, Pixels::Rgb565(&mut data), opaque, CompositeOp::Src);
+        assert_eq!(data, vec![24 << 11 | 25 << 5 | 6; WIDTH]);
+    }
+}
+
+fn assert_floats_close(actual: &[f32], expected: &[f32]) {
+    for (i, (&actual, &expected)) in actual.iter().zip(expected).enumerate() {
+        let close = (actual - expected).abs() <= 1e-4 * expected.abs().max(1.0);
+        assert!(close, "value {}: {} != {}", i, actual, expected);
+    }
+}
+
+/// Premultiplied float pixels with varied alpha, where colors of the opaque pixels exceed 1.
+fn float_pattern() -> Vec<f32> {
+    let mut data = Vec::new();
+    for i in 0..WIDTH {
+        let a = if i % 5 == 1 {
+            1.0
+        } else {
+            (i * 71 % 256) as f32 / 255.0
+        };
+        for c in 0..3 {
+            let value = ((i * 53 + c * 89 + 17) % 256) as f32 / 255.0;
+            data.push(if a == 1.0 { 4.0 * value } else { value * a });
+        }
+        data.push(a);
+    }
+    data
+}
+
+#[test]
+fn float_format_round_trips() {
+    for backend in backends() {
+        for linear in [false, true] {
+            let expected = float_pattern();
+            let mut data = expected.clone();
+
+            let mut renderer = Renderer::new();
+            renderer.set_backend(backend);
+            renderer.set_linear_blending(linear);
+            let mut target = renderer.attach_pixels(Pixels::RgbaF32(&mut data), WIDTH, 1);
+            let path = rect(0.0, 0.0, WIDTH as f32, 1.0);
+            let opaque = Color::rgba(0, 0, 0, 255);
+            target.fill_path(
+                &path,
+                Affine::id(),
+                FillRule::NonZero,
+                opaque,
+                CompositeOp::DstIn,
+            );
+            drop(target);
+
+            assert_floats_close(&data, &expected);
+        }
+    }
+}
+
+#[test]
+fn float_layers_keep_values_above_one() {
+    let white = Color::rgba(255, 255, 255, 255);
+    let path = rect(0.0, 0.0, WIDTH as f32, 1.0);
+    let draw_twice = |target: &mut RenderTarget| {
+        for _ in 0..2 {
+            target.fill_path(
+                &path,
+                Affine::id(),
+                FillRule::NonZero,
+                white,
+                CompositeOp::Plus,
+            );
+        }
+    };
+
+    for backend in backends() {
+        let mut renderer = Renderer::new();
+        renderer.set_backend(backend);
+
+        let mut data = vec![0.0; 4 * WIDTH];
+        let mut target = renderer.attach_pixels(Pixels::RgbaF32(&mut data), WIDTH, 1);
+        target.with_layer(1.0, CompositeOp::SrcOver, None, draw_twice);
+        drop(target);
+        assert_floats_close(&data, &[2.0; 4].repeat(WIDTH));
+
+        let mut data = vec![0.0; 4 * WIDTH];
+        let mut target = renderer.attach_pixels(Pixels::RgbaF32(&mut data), WIDTH, 1);
+        target.with_blur(0.0, draw_twice);
+        drop(target);
+        assert_floats_close(&data, &[2.0; 4].repeat(WIDTH));
+    }
+}
+
+#[test]
+fn float_colors_keep_values_above_one() {
+    let color = ColorF32::rgba(2.0, 0.5, 3.0, 0.5);
+    for backend in backends() {
+        let mut data = vec![0.0; 4 * WIDTH];
+        fill(backend, Pixels::RgbaF32(&mut data), color, CompositeOp::Src);
+        assert_floats_close(&data, &[1.0, 0.25, 1.5, 0.5].repeat(WIDTH));
+
+        // 8-bit formats limit colors to alpha.
+        let mut data = vec![0; WIDTH];
+        fill(backend, Pixels::Argb32(&mut data), color, CompositeOp::Src);
+        assert_eq!(data, vec![0x8080_4080; WIDTH]);
+    }
+}
+
+#[test]
+fn float_gradients_interpolate_between_stops() {
+    // Across this many pixels, the nearest entry of a 256-entry table would be off by up to 1/510
+    // of the range of colors.
+    const LEN: usize = 1000;
+    let stops = vec![
+        Stop::new(0.0, ColorF32::rgba(0.0, 1.0, 0.0, 1.0)),
+        Stop::new(1.0, ColorF32::rgba(4.0, 0.0, 0.5, 1.0)),
+    ];
+    let gradient = Gradient::linear(Point::new(0.0, 0.0), Point::new(LEN as f32, 0.0), stops);
+    let expected: Vec<f32> = (0..LEN)
+        .flat_map(|x| {
+            let t = (x as f32 + 0.5) / LEN as f32;
+            [4.0 * t, 1.0 - t, 0.5 * t, 1.0]
+        })
+        .collect();
+
+    for backend in backends() {
+        let mut renderer = Renderer::new();
+        renderer.set_backend(backend);
+        let mut data = vec![0.0; 4 * LEN];
+        let mut target = renderer.attach_pixels(Pixels::RgbaF32(&mut data), LEN, 1);
+        let path = rect(0.0, 0.0, LEN as f32, 1.0);
+        target.fill_path(
+            &path,
+            Affine::id(),
+            FillRule::NonZero,
+            &gradient,
+            CompositeOp::Src,
+        );
+        drop(target);
+
+        assert_floats_close(&data, &expected);
+    }
+}
+
+/// Fills 8 rows of pixels with `paint`, covering every row of the dithering pattern. Dithering is
+/// left at its default unless `dither` is given.
+fn fill_rows<'p>(
+    backend: Backend,
+    pixels: Pixels,
+    paint: impl Into<Paint<'p>>,
+    op: CompositeOp,
+    dither: Option<bool>,
+) {
+    let mut renderer = Renderer::new();
+    renderer.set_backend(backend);
+    if let Some(dither) = dither {
+        renderer.set_dithering(dither);
+    }
+    let mut target = renderer.attach_pixels(pixels, WIDTH, 8);
+    let path = rect(0.0, 0.0, WIDTH as f32, 8.0);
+    target.fill_path(&path, Affine::id(), FillRule::NonZero, paint, op);
+}
+
+/// An opaque color whose channels lie between 8-bit values.
+fn between_levels() -> ColorF32 {
+    ColorF32::rgba(20.25 / 255.0, 100.75 / 255.0, 200.5 / 255.0, 1.0)
+}
+
+#[test]
+fn dithering_is_off_by_default() {
+    let render = |dither| {
+        let mut data = vec![0xFF00_0000; 8 * WIDTH];
+        let color = between_levels();
+        fill_rows(
+            Backend::Scalar,
+            Pixels::Argb32(&mut data),
+            color,
+            CompositeOp::SrcOver,
+            dither,
+        );
+        data
+    };
+    assert_eq!(render(None), render(Some(false)));
+    assert_ne!(render(None), render(Some(true)));
+}
+
+/// Asserts that every value of a channel is one of the two levels around `level`, and that both of
+/// them occur.
+fn assert_nearest_levels(values: impl Iterator<Item = u32>, level: f32) {
+    let mut counts = [0; 2];
+    for value in values {
+        let index = value as f32 - level.floor();
+        assert!(index == 0.0 || index == 1.0, "{} for {}", value, level);
+        counts[index as usize] += 1;
+    }
+    assert!(counts[0] > 0 && counts[1] > 0, "{:?} for {}", counts, level);
+}
+
+#[test]
+fn dithering_stays_within_half_a_level() {
+    for backend in backends() {
+        // Offsets of less than half a level keep every value at one of the two levels around it.
+        let color = between_levels();
+        let mut data = vec![0xFF00_0000; 8 * WIDTH];
+        let op = CompositeOp::SrcOver;
+        fill_rows(backend, Pixels::Argb32(&mut data), color, op, Some(true));
+        for (shift, level) in [(16, color.r), (8, color.g), (0, color.b)] {
+            let values = data.iter().map(|&pixel| pixel >> shift & 0xFF);
+            assert_nearest_levels(values, 255.0 * level);
+        }
+
+        // Green has twice as many levels as red and blue.
+        let color = ColorF32::rgba(10.25 / 31.0, 20.25 / 63.0, 25.75 / 31.0, 1.0);
+        let mut data = vec![0; 8 * WIDTH];
+        fill_rows(backend, Pixels::Rgb565(&mut data), color, op, Some(true));
+        for (shift, mask, level) in [
+            (11, 0x1F, 31.0 * color.r),
+            (5, 0x3F, 63.0 * color.g),
+            (0, 0x1F, 31.0 * color.b),
+        ] {
+            let values = data.iter().map(|&pixel| pixel as u32 >> shift & mask);
+            assert_nearest_levels(values, level);
+        }
+    }
+}
+
+#[test]
+fn dithering_leaves_exact_colors_unchanged() {
+    // Source-atop over opaque pixels gives the source color, without taking the shortcut for
+    // opaque colors which skips blending and dithering.
+    let op = CompositeOp::SrcAtop;
+    for backend in backends() {
+        for color in [
+            Color::rgba(20, 100, 200, 255),
+            Color::rgba(0, 255, 1, 255),
+            Color::rgba(255, 255, 255, 255),
+        ] {
+            let mut data = vec![0xFF00_0000; 8 * WIDTH];
+            fill_rows(backend, Pixels::Argb32(&mut data), color, op, Some(true));
+            assert_eq!(data, vec![u32::from(color); 8 * WIDTH]);
+        }
+
+        let color = Color::rgba(255, 0, 255, 255);
+        let mut data = vec![0; 8 * WIDTH];
+        fill_rows(backend, Pixels::Rgb565(&mut data), color, op, Some(true));
+        assert_eq!(data, vec![0xF81F; 8 * WIDTH]);
+    }
+}
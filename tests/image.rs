@@ -0,0 +1,96 @@
+use flicker::{
+    Affine, Bbox, CompositeOp, Extend, FillRule, Filter, Image, Pattern, RenderTarget, Renderer,
+};
+
+mod common;
+
+use common::{assert_close, rect};
+
+const WIDTH: usize = 40;
+const HEIGHT: usize = 30;
+
+/// A small image of premultiplied pixels with varied color and alpha.
+fn pixels() -> Vec<u32> {
+    (0..7 * 5)
+        .map(|i| {
+            let a = (i * 97 % 256) as u32 | 0x40;
+            let [r, g, b] = [29, 113, 201].map(|c| ((i * 67 + c) % 256) as u32 * a / 255);
+            a << 24 | r << 16 | g << 8 | b
+        })
+        .collect()
+}
+
+fn render(draw: impl Fn(&mut RenderTarget)) -> Vec<u32> {
+    let mut data = vec![0xFF20_4060; WIDTH * HEIGHT];
+    let mut renderer = Renderer::new();
+    draw(&mut renderer.attach(&mut data, WIDTH, HEIGHT));
+    data
+}
+
+fn src_rect(x0: i32, y0: i32, x1: i32, y1: i32) -> Bbox {
+    Bbox { x0, y0, x1, y1 }
+}
+
+#[test]
+fn axis_aligned_images_match_patterns() {
+    let pixels = pixels();
+    let image = Image::new(&pixels, 7, 5, 7);
+    let full = src_rect(0, 0, 7, 5);
+
+    // Each of these transforms scales by whole numbers, so the image is drawn as a rectangle
+    // rather than filled as a path.
+    let transforms = [
+        Affine::translate(3.0, 2.0),
+        Affine::translate(3.0, 2.0) * Affine::scale(2.0),
+        Affine::translate(-4.0, 1.0) * Affine::scale(3.0),
+        Affine::translate(5.0, 3.0) * Affine::new([3.0, 0.0, 0.0, 0.0, 4.0, 0.0]),
+    ];
+    for transform in transforms {
+        for filter in [Filter::Nearest, Filter::Bilinear, Filter::Bicubic] {
+            let actual = render(|target| {
+                target.draw_image(image, full, transform, 1.0, filter, CompositeOp::SrcOver);
+            });
+            let expected = render(|target| {
+                let pattern = Pattern {
+                    image,
+                    transform: Affine::id(),
+                    extend: Extend::Pad,
+                    filter,
+                };
+                let path = rect(0.0, 0.0, 7.0, 5.0);
+                target.fill_path(
+                    &path,
+                    transform,
+                    FillRule::NonZero,
+                    pattern,
+                    CompositeOp::SrcOver,
+                );
+            });
+            assert_close(&actual, &expected);
+        }
+    }
+}
+
+#[test]
+fn source_rect_is_cut_to_image() {
+    let pixels = pixels();
+    let image = Image::new(&pixels, 7, 5, 7);
+    let transform = Affine::translate(10.0, 8.0) * Affine::scale(2.0);
+
+    for filter in [Filter::Nearest, Filter::Bilinear] {
+        for transform in [transform, transform * Affine::rotate(0.2)] {
+            // The parts of the source rectangle outside the image are left out, without moving
+            // the rest of it.
+            let actual = render(|target| {
+                let src = src_rect(-2, -3, 5, 9);
+                target.draw_image(image, src, transform, 1.0, filter, CompositeOp::SrcOver);
+            });
+            let expected = render(|target| {
+                let src = src_rect(0, 0, 5, 5);
+                let transform = transform * Affine::translate(2.0, 3.0);
+                target.draw_image(image, src, transform, 1.0, filter, CompositeOp::SrcOver);
+            });
+            assert_eq!(actual, expected);
+        }
+    }
+}
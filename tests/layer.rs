@@ -0,0 +1,77 @@
+use flicker::{Affine, Bbox, BlendMode, Color, CompositeOp, FillRule, RenderTarget, Renderer};
+
+mod common;
+
+use common::rect;
+
+const WIDTH: usize = 24;
+const HEIGHT: usize = 16;
+
+fn render(draw: impl Fn(&mut RenderTarget)) -> Vec<u32> {
+    let mut data = vec![0; WIDTH * HEIGHT];
+    let mut renderer = Renderer::new();
+    draw(&mut renderer.attach(&mut data, WIDTH, HEIGHT));
+    data
+}
+
+/// Fills two overlapping rectangles, which together cover pixels 2 through 19 of rows 3 through
+/// 12, and overlap in pixels 8 through 13.
+fn fill_overlapping(target: &mut RenderTarget, color: Color) {
+    for path in [rect(2.0, 3.0, 14.0, 13.0), rect(8.0, 3.0, 20.0, 13.0)] {
+        target.fill_path(
+            &path,
+            Affine::id(),
+            FillRule::NonZero,
+            color,
+            CompositeOp::SrcOver,
+        );
+    }
+}
+
+#[test]
+fn layer_opacity_applies_once() {
+    let white = Color::rgba(255, 255, 255, 255);
+    let data = render(|target| {
+        target.with_layer(0.5, BlendMode::Normal, None, |target| {
+            fill_overlapping(target, white);
+        });
+    });
+
+    // The overlap is no darker than the rest of the shapes, as it would be if each shape were
+    // drawn with half opacity.
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let inside = (2..20).contains(&x) && (3..13).contains(&y);
+            let expected = if inside { 0x8080_8080 } else { 0 };
+            assert_eq!(data[y * WIDTH + x], expected, "({}, {})", x, y);
+        }
+    }
+
+    let half = Color::rgba(255, 255, 255, 128);
+    let data = render(|target| fill_overlapping(target, half));
+    assert_eq!(data[5 * WIDTH + 4], 0x8080_8080);
+    assert_eq!(data[5 * WIDTH + 10], 0xC0C0_C0C0);
+}
+
+#[test]
+fn layer_clip_limits_drawing() {
+    let white = Color::rgba(255, 255, 255, 255);
+    let clip = Bbox {
+        x0: 5,
+        y0: 0,
+        x1: 11,
+        y1: HEIGHT as i32,
+    };
+    let data = render(|target| {
+        target.with_layer(1.0, BlendMode::Normal, Some(clip), |target| {
+            fill_overlapping(target, white);
+        });
+    });
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let inside = (5..11).contains(&x) && (3..13).contains(&y);
+            let expected = if inside { 0xFFFF_FFFF } else { 0 };
+            assert_eq!(data[y * WIDTH + x], expected, "({}, {})", x, y);
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use flicker::{Affine, Color, CompositeOp, FillRule, Font, Renderer};
+
+mod common;
+
+use common::{backends, channels, rect};
+
+const WIDTH: usize = 40;
+const HEIGHT: usize = 16;
+
+#[test]
+fn linear_blending_weights_by_light() {
+    // Half of white over black is half of the light, which sRGB encodes as 188 rather than 128.
+    for (linear, expected) in [(false, 128), (true, 188)] {
+        for backend in backends() {
+            let mut data = vec![0xFF00_0000; WIDTH * HEIGHT];
+            let mut renderer = Renderer::new();
+            renderer.set_backend(backend);
+            renderer.set_linear_blending(linear);
+            let mut target = renderer.attach(&mut data, WIDTH, HEIGHT);
+            let path = rect(0.0, 0.0, WIDTH as f32, HEIGHT as f32);
+            let white = Color::rgba(255, 255, 255, 128);
+            target.fill_path(
+                &path,
+                Affine::id(),
+                FillRule::NonZero,
+                white,
+                CompositeOp::SrcOver,
+            );
+            drop(target);
+
+            for &pixel in &data {
+                let [a, r, g, b] = channels(pixel);
+                assert_eq!(a, 255);
+                for c in [r, g, b] {
+                    assert!((c - expected).abs() <= 1, "{:?}: {}", backend, c);
+                }
+            }
+        }
+    }
+}
+
+/// Draws text in opaque black and returns the alpha of each pixel.
+fn text(gamma: f32) -> Vec<i32> {
+    let font = Font::from_bytes(
+        include_bytes!("../examples/res/SourceSansPro-Regular.otf"),
+        0,
+    )
+    .unwrap();
+    let mut data = vec![0; WIDTH * HEIGHT];
+    let mut renderer = Renderer::new();
+    renderer.set_text_gamma(gamma);
+    let mut target = renderer.attach(&mut data, WIDTH, HEIGHT);
+    let black = Color::rgba(0, 0, 0, 255);
+    let transform = Affine::translate(2.0, 0.0);
+    target.fill_text("Ag", &font, 13.0, transform, black, CompositeOp::SrcOver);
+    drop(target);
+    data.iter().map(|&pixel| channels(pixel)[0]).collect()
+}
+
+#[test]
+fn text_gamma_changes_coverage() {
+    let plain = text(1.0);
+    let heavy = text(2.2);
+    let light = text(0.5);
+
+    // Gamma only changes partially covered pixels, making them more covered above 1 and less
+    // covered below it.
+    let mut partial = 0;
+    for ((&p, &h), &l) in plain.iter().zip(&heavy).zip(&light) {
+        if p == 0 || p == 255 {
+            assert_eq!((h, l), (p, p));
+        } else {
+            assert!(h >= p && l <= p, "{} {} {}", p, h, l);
+            if h > p && l < p {
+                partial += 1;
+            }
+        }
+    }
+    assert!(partial > 10, "{}", partial);
+}
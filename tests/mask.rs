@@ -0,0 +1,88 @@
+use flicker::{Affine, Backend, Color, CompositeOp, FillRule, MaskType, Renderer};
+
+mod common;
+
+use common::{assert_close, backends, rect};
+
+const SIZE: usize = 20;
+
+/// Fills the target with opaque white through a mask which covers the left half of the target
+/// with `color`, and returns the result.
+fn draw_masked(backend: Backend, mask_type: MaskType, color: Color) -> Vec<u32> {
+    let mut data = vec![0; SIZE * SIZE];
+    let mut renderer = Renderer::new();
+    renderer.set_backend(backend);
+    let mut target = renderer.attach(&mut data, SIZE, SIZE);
+
+    let half = rect(0.0, 0.0, (SIZE / 2) as f32, SIZE as f32);
+    let full = rect(0.0, 0.0, SIZE as f32, SIZE as f32);
+    target.with_mask(
+        mask_type,
+        |target| {
+            target.fill_path(
+                &half,
+                Affine::id(),
+                FillRule::NonZero,
+                color,
+                CompositeOp::SrcOver,
+            );
+        },
+        |target| {
+            let white = Color::rgba(255, 255, 255, 255);
+            target.fill_path(
+                &full,
+                Affine::id(),
+                FillRule::NonZero,
+                white,
+                CompositeOp::SrcOver,
+            );
+        },
+    );
+    drop(target);
+
+    data
+}
+
+/// The result of `draw_masked`, given the coverage of the left half.
+fn expected(coverage: u32) -> Vec<u32> {
+    (0..SIZE * SIZE)
+        .map(|i| {
+            if i % SIZE < SIZE / 2 {
+                coverage * 0x01010101
+            } else {
+                0
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn alpha_mask() {
+    for backend in backends() {
+        for alpha in [0, 1, 128, 254, 255] {
+            let color = Color::rgba(10, 200, 30, alpha);
+            let data = draw_masked(backend, MaskType::Alpha, color);
+            assert_close(&data, &expected(alpha as u32));
+        }
+    }
+}
+
+#[test]
+fn luminance_mask() {
+    // The Rec. 709 coefficients, applied to sRGB-encoded values and premultiplied by alpha.
+    let colors = [
+        (Color::rgba(255, 255, 255, 255), 255),
+        (Color::rgba(255, 0, 0, 255), 54),
+        (Color::rgba(0, 255, 0, 255), 182),
+        (Color::rgba(0, 0, 255, 255), 18),
+        (Color::rgba(255, 255, 255, 128), 128),
+        (Color::rgba(0, 0, 0, 255), 0),
+    ];
+
+    for backend in backends() {
+        for (color, coverage) in colors {
+            let data = draw_masked(backend, MaskType::Luminance, color);
+            assert_close(&data, &expected(coverage));
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use flicker::{
+    Affine, Color, CompositeOp, Dash, LineCap, LineJoin, Path, Point, Renderer, StrokeStyle,
+};
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 48;
+
+/// Strokes a path in opaque black and returns the alpha of each pixel.
+fn stroke(path: &Path, style: &StrokeStyle) -> Vec<u8> {
+    let mut data = vec![0; WIDTH * HEIGHT];
+    let mut renderer = Renderer::new();
+    let mut target = renderer.attach(&mut data, WIDTH, HEIGHT);
+    let black = Color::rgba(0, 0, 0, 255);
+    target.stroke_path(path, style, Affine::id(), black, CompositeOp::SrcOver);
+    drop(target);
+    data.iter().map(|pixel| (pixel >> 24) as u8).collect()
+}
+
+fn alpha(data: &[u8], x: usize, y: usize) -> u8 {
+    data[y * WIDTH + x]
+}
+
+fn polyline(points: &[(f32, f32)]) -> Path {
+    let mut path = Path::new();
+    path.move_to(Point::new(points[0].0, points[0].1));
+    for &(x, y) in &points[1..] {
+        path.line_to(Point::new(x, y));
+    }
+    path
+}
+
+fn joined(join: LineJoin, miter_limit: f32) -> Vec<u8> {
+    // A right angle with its outer corner at (40, 40).
+    let path = polyline(&[(10.0, 30.0), (30.0, 30.0), (30.0, 10.0)]);
+    let mut style = StrokeStyle::new(20.0);
+    style.join = join;
+    style.miter_limit = miter_limit;
+    stroke(&path, &style)
+}
+
+#[test]
+fn joins() {
+    // Pixels in the outer corner: one at the corner, one outside the bevel but inside the arc of
+    // a round join, and one inside the bevel.
+    let corner = (39, 39);
+    let arc = (35, 35);
+    let bevel = (33, 33);
+
+    let miter = joined(LineJoin::Miter, 4.0);
+    let round = joined(LineJoin::Round, 4.0);
+    let bevel_join = joined(LineJoin::Bevel, 4.0);
+    for (data, expected) in [
+        (&miter, [255, 255, 255]),
+        (&round, [0, 255, 255]),
+        (&bevel_join, [0, 0, 255]),
+    ] {
+        let actual = [corner, arc, bevel].map(|(x, y)| alpha(data, x, y));
+        assert_eq!(actual, expected);
+    }
+
+    // The inner side of the join is filled without any gaps.
+    assert_eq!(alpha(&miter, 22, 22), 255);
+}
+
+#[test]
+fn miter_limit() {
+    // A right angle has a miter length of sqrt(2) times the stroke width.
+    assert_eq!(joined(LineJoin::Miter, 1.42), joined(LineJoin::Miter, 4.0));
+    assert_eq!(joined(LineJoin::Miter, 1.41), joined(LineJoin::Bevel, 4.0));
+}
+
+fn capped(path: &Path, cap: LineCap) -> Vec<u8> {
+    let mut style = StrokeStyle::new(6.0);
+    style.cap = cap;
+    stroke(path, &style)
+}
+
+#[test]
+fn caps() {
+    // The stroke covers rows 7 through 12 and ends at x = 10 and x = 30.
+    let line = polyline(&[(10.0, 10.0), (30.0, 10.0)]);
+    let butt = capped(&line, LineCap::Butt);
+    let square = capped(&line, LineCap::Square);
+    let round = capped(&line, LineCap::Round);
+
+    for (x, y) in [(29, 9), (29, 12), (10, 7)] {
+        assert_eq!(alpha(&butt, x, y), 255);
+    }
+    for (x, y) in [(30, 9), (9, 10), (31, 10)] {
+        assert_eq!(alpha(&butt, x, y), 0);
+    }
+
+    // Square caps extend by half the width, to x = 7 and x = 33.
+    for (x, y) in [(32, 7), (32, 12), (7, 9), (31, 10)] {
+        assert_eq!(alpha(&square, x, y), 255);
+    }
+    for (x, y) in [(33, 10), (6, 9)] {
+        assert_eq!(alpha(&square, x, y), 0);
+    }
+
+    // Round caps reach as far as square caps in the middle, but not in the corners.
+    for (x, y) in [(31, 8), (31, 11), (8, 9)] {
+        assert_eq!(alpha(&round, x, y), 255);
+    }
+    for (x, y) in [(32, 9), (32, 7), (7, 12)] {
+        let a = alpha(&round, x, y);
+        assert!(a > 0 && a < 255, "{}", a);
+    }
+    assert_eq!(alpha(&round, 33, 10), 0);
+}
+
+#[test]
+fn zero_length_subpaths() {
+    let dot = polyline(&[(20.0, 10.0), (20.0, 10.0)]);
+
+    assert!(capped(&dot, LineCap::Butt).iter().all(|&a| a == 0));
+
+    let square = capped(&dot, LineCap::Square);
+    for (x, y) in [(17, 7), (22, 12), (20, 10)] {
+        assert_eq!(alpha(&square, x, y), 255);
+    }
+    assert_eq!(alpha(&square, 23, 10), 0);
+
+    let round = capped(&dot, LineCap::Round);
+    assert_eq!(alpha(&round, 19, 9), 255);
+    let a = alpha(&round, 17, 7);
+    assert!(a > 0 && a < 255, "{}", a);
+}
+
+#[test]
+fn dash_offset_at_end_of_dash() {
+    // The offset puts the start of the line at the end of the first dash, so the line starts with
+    // a gap from 10 to 15 rather than an empty dash, which round or square caps would draw as a dot.
+    let line = polyline(&[(10.0, 10.0), (40.0, 10.0)]);
+    for cap in [LineCap::Round, LineCap::Square] {
+        let mut style = StrokeStyle::new(4.0);
+        style.cap = cap;
+        style.dash = Some(Dash::new(vec![5.0, 5.0], 5.0));
+        let data = stroke(&line, &style);
+
+        for x in 6..13 {
+            for y in 7..13 {
+                assert_eq!(alpha(&data, x, y), 0, "{:?} at ({}, {})", cap, x, y);
+            }
+        }
+        assert_eq!(alpha(&data, 15, 10), 255);
+    }
+}
+
+#[test]
+fn bounds_include_miters_and_square_caps() {
+    // The miter at (50, 30) has a length of about 4.1 times the width, so its tip reaches
+    // x = 58.2, well past half the width from the path.
+    let path = polyline(&[(10.0, 40.0), (50.0, 30.0), (10.0, 20.0)]);
+    let mut style = StrokeStyle::new(4.0);
+    style.miter_limit = 10.0;
+    let data = stroke(&path, &style);
+    assert!(alpha(&data, 56, 29) > 0);
+    assert!(alpha(&data, 56, 30) > 0);
+
+    // The corners of the square cap at (30, 30) lie sqrt(2) times the half-width away from it, at
+    // (35.7, 30) and (30, 35.7).
+    let path = polyline(&[(10.0, 10.0), (30.0, 30.0)]);
+    let mut style = StrokeStyle::new(8.0);
+    style.cap = LineCap::Square;
+    let data = stroke(&path, &style);
+    assert!(alpha(&data, 35, 29) > 0);
+    assert!(alpha(&data, 29, 35) > 0);
+}